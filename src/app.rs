@@ -1,12 +1,27 @@
+use std::fs;
+use std::path::Path;
 use std::sync::Arc;
 
+use chrono::Utc;
 use hyperliquid_rust_sdk::BaseUrl;
+use rust_decimal::Decimal;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+use tracing::info;
 
-use crate::config::{Settings, StrategyInstanceConfig};
-use crate::engine::runner::Engine;
+use crate::backtest::{BacktestReport, Backtester, load_candles};
+use crate::config::{ExecutionMode, Settings, StrategyInstanceConfig};
+use crate::engine::alerts::AlertDispatcher;
+use crate::engine::kill_switch::KillSwitch;
+use crate::engine::risk::RiskLimits;
+use crate::engine::runner::{Engine, StrategySlot};
 use crate::errors::{AppError, AppResult};
-use crate::exchange::{self, InfoService, MarketStream, OrderRouter, PositionManager};
+use crate::exchange::{
+    self, AssetMetaCache, ExecutionVenue, FillEvent, InfoService, OrderManager, OrderRouter,
+    OrderUpdateEvent, PositionManager, Reconciler, RequestBudget, SimulatedVenue,
+};
 use crate::marketdata::feeds::FeedCoordinator;
+use crate::marketdata::order_book::OrderBooks;
 use crate::storage::journal::Journal;
 use crate::storage::persistence::SnapshotStore;
 use crate::strategies::{
@@ -18,6 +33,14 @@ pub struct App {
     settings: Settings,
 }
 
+struct ExecutionSetup {
+    venue: Arc<dyn ExecutionVenue>,
+    simulator: Option<Arc<SimulatedVenue>>,
+    fills: Option<UnboundedReceiver<FillEvent>>,
+    order_updates: Option<UnboundedReceiver<OrderUpdateEvent>>,
+    reconciler: Option<Reconciler>,
+}
+
 impl App {
     pub fn new(settings: Settings) -> Self {
         register_builtin_strategies();
@@ -25,52 +48,232 @@ impl App {
     }
 
     pub async fn run(self) -> AppResult<()> {
-        let strategy_cfg = self.settings.ensure_strategy()?.clone();
-        let base_url = match self.settings.exchange.network.to_lowercase().as_str() {
-            "testnet" => BaseUrl::Testnet,
-            "local" => BaseUrl::Localhost,
-            _ => BaseUrl::Mainnet,
-        };
-
-        let info = InfoService::connect(base_url).await?;
-        let snapshot_store = SnapshotStore::new(&self.settings.persistence.snapshot_path);
-        let signer_key = self.resolve_signer_key()?;
-        let order_router = Arc::new(OrderRouter::new(base_url, &signer_key).await?);
-        let wallet_address = order_router.wallet_address();
-        let asset = extract_param_string(&strategy_cfg, "asset", "BTC");
-        let candle_interval = extract_param_string(&strategy_cfg, "candle_interval", "1m");
+        let strategy_cfgs: Vec<StrategyInstanceConfig> = self
+            .settings
+            .enabled_strategies()?
+            .into_iter()
+            .cloned()
+            .collect();
+        let base_url = self.settings.exchange.base_url();
 
-        let market_stream =
-            MarketStream::connect_candles(info.clone(), asset.clone(), candle_interval, 1024)
-                .await?;
-
-        let feed = FeedCoordinator::new(market_stream);
+        let budget = Arc::new(RequestBudget::per_minute(
+            self.settings.exchange.rate_limit_per_minute,
+        ));
+        let info = InfoService::connect(base_url, budget.clone()).await?;
+        let snapshot_dir = self
+            .settings
+            .persistence
+            .snapshot_dir(self.settings.exchange.mode);
+        if self.settings.exchange.mode == ExecutionMode::Live {
+            adopt_legacy_snapshots(
+                Path::new(&self.settings.persistence.snapshot_path),
+                &snapshot_dir,
+            )?;
+        }
+        let snapshot_store = SnapshotStore::new(&snapshot_dir)
+            .with_history(self.settings.persistence.snapshot_history);
         let positions = Arc::new(PositionManager::new());
+        let orders = Arc::new(OrderManager::new());
+        let books = Arc::new(OrderBooks::new());
         let journal = Arc::new(
             Journal::new(&self.settings.persistence.journal_path)
                 .map_err(|e| AppError::Other(e.to_string()))?,
         );
 
-        let builder_ctx = StrategyBuilderContext {
-            base_url,
-            info: info.clone(),
-            snapshot_store: snapshot_store.clone(),
-        };
+        let ExecutionSetup {
+            venue,
+            simulator,
+            fills: fill_rx,
+            order_updates,
+            reconciler,
+        } = self
+            .execution_setup(base_url, &info, &journal, &positions)
+            .await?;
+        let mut slots = Vec::with_capacity(strategy_cfgs.len());
+        for strategy_cfg in &strategy_cfgs {
+            let instance_id = strategy_cfg.instance_id();
+            let builder_ctx = StrategyBuilderContext {
+                instance_id: instance_id.clone(),
+                base_url,
+                info: Some(info.clone()),
+                snapshot_store: snapshot_store.clone(),
+            };
+            let strategy =
+                build_strategy(&strategy_cfg.id, strategy_cfg.params.clone(), builder_ctx)?;
+            if fill_rx.is_none() && strategy.uses_brackets() {
+                return Err(AppError::Config(format!(
+                    "{instance_id} places TP/SL brackets, which need the user fills stream"
+                )));
+            }
+            let ctx = StrategyContext::new(
+                &instance_id,
+                venue.clone(),
+                positions.clone(),
+                orders.clone(),
+                books.clone(),
+                journal.clone(),
+            );
 
-        let strategy = build_strategy(&strategy_cfg.id, strategy_cfg.params.clone(), builder_ctx)?;
+            info!(instance = %instance_id, strategy = %strategy_cfg.id, "strategy instance ready");
+            slots.push(StrategySlot::new(instance_id, strategy, ctx));
+        }
 
-        let ctx = StrategyContext::new(order_router.clone(), positions.clone(), journal.clone());
-        let fill_rx = match exchange::user_fills_stream(info.clone(), wallet_address).await {
-            Ok(rx) => Some(rx),
-            Err(e) => {
-                tracing::warn!(error = %e, "unable to subscribe to user fills");
-                None
+        let subscriptions = slots
+            .iter()
+            .flat_map(|slot| slot.subscriptions().iter().cloned())
+            .collect::<Vec<_>>();
+        let mut feed = FeedCoordinator::connect(&info, subscriptions, 1024).await?;
+        if let Some(simulator) = &simulator {
+            let observer = simulator.clone();
+            feed = feed.tap(1024, move |event| observer.on_market_event(event));
+        }
+
+        let risk = RiskLimits::new(self.settings.risk.clone());
+        let kill_switch = KillSwitch::new(
+            self.settings.kill_switch.clone(),
+            snapshot_store.clone(),
+            venue.clone(),
+            journal.clone(),
+        )?;
+        let mut engine = Engine::new(
+            slots,
+            feed,
+            positions.clone(),
+            orders,
+            books,
+            risk,
+            kill_switch,
+        );
+        if let Some(fills) = fill_rx {
+            engine = engine.with_fills(fills);
+        }
+        let (stop_tx, stop_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            shutdown_signal().await;
+            let _ = stop_tx.send(());
+            shutdown_signal().await;
+            tracing::warn!("second shutdown signal, exiting without cleanup");
+            std::process::exit(130);
+        });
+        engine = engine
+            .with_alerts(AlertDispatcher::from_config(
+                &self.settings.alerts,
+                journal.clone(),
+            )?)
+            .with_shutdown(stop_rx, self.settings.shutdown.clone())
+            .with_persistence(
+                snapshot_store,
+                self.settings.persistence.snapshot_interval(),
+                journal,
+            );
+        if let Some(updates) = order_updates {
+            engine = engine.with_order_updates(updates);
+        }
+        if let Some(reconciler) = reconciler {
+            engine = engine.with_reconciler(reconciler);
+        }
+        let result = engine.run().await;
+        let stats = budget.stats();
+        info!(
+            throttled = stats.throttled,
+            waited_ms = stats.waited.as_millis() as u64,
+            "request budget usage"
+        );
+        result
+    }
+
+    async fn execution_setup(
+        &self,
+        base_url: BaseUrl,
+        info: &InfoService,
+        journal: &Arc<Journal>,
+        positions: &Arc<PositionManager>,
+    ) -> AppResult<ExecutionSetup> {
+        let meta = Arc::new(AssetMetaCache::load(info).await?);
+        match self.settings.exchange.mode {
+            ExecutionMode::Live => {
+                let signer_key = self.resolve_signer_key()?;
+                let order_router =
+                    Arc::new(OrderRouter::new(base_url, &signer_key, info.clone(), meta).await?);
+                let wallet_address = order_router.wallet_address();
+                let fills = match exchange::user_fills_stream(info.clone(), wallet_address).await {
+                    Ok(rx) => Some(rx),
+                    Err(e) => {
+                        tracing::warn!(error = %e, "unable to subscribe to user fills");
+                        None
+                    }
+                };
+                let order_updates =
+                    match exchange::order_updates_stream(info.clone(), wallet_address).await {
+                        Ok(rx) => Some(rx),
+                        Err(e) => {
+                            tracing::warn!(error = %e, "unable to subscribe to order updates");
+                            None
+                        }
+                    };
+                let reconciler = Reconciler::new(
+                    info.clone(),
+                    wallet_address,
+                    self.settings.reconciliation.clone(),
+                    journal.clone(),
+                );
+                Ok(ExecutionSetup {
+                    venue: order_router,
+                    simulator: None,
+                    fills,
+                    order_updates,
+                    reconciler: Some(reconciler),
+                })
+            }
+            ExecutionMode::Paper => {
+                info!("paper trading enabled, orders are filled by the local simulator");
+                let (simulator, fills) = SimulatedVenue::new();
+                let simulator =
+                    Arc::new(simulator.with_meta(meta).with_positions(positions.clone()));
+                Ok(ExecutionSetup {
+                    venue: simulator.clone(),
+                    simulator: Some(simulator),
+                    fills: Some(fills),
+                    order_updates: None,
+                    reconciler: None,
+                })
             }
+        }
+    }
+
+    pub async fn backtest(
+        self,
+        data: impl AsRef<Path>,
+        initial_capital: Decimal,
+        output_dir: impl AsRef<Path>,
+    ) -> AppResult<BacktestReport> {
+        let strategy_cfg = self.settings.ensure_strategy()?.clone();
+        let instance_id = strategy_cfg.instance_id();
+        let candles = load_candles(data)?;
+        let run_dir = output_dir
+            .as_ref()
+            .join(Utc::now().format("%Y%m%dT%H%M%S").to_string());
+
+        let journal = Arc::new(
+            Journal::new(run_dir.join("journal.log"))
+                .map_err(|e| AppError::Other(e.to_string()))?,
+        );
+        let builder_ctx = StrategyBuilderContext {
+            instance_id: instance_id.clone(),
+            base_url: self.settings.exchange.base_url(),
+            info: None,
+            snapshot_store: SnapshotStore::new(run_dir.join("snapshots")),
         };
+        let strategy = build_strategy(&strategy_cfg.id, strategy_cfg.params.clone(), builder_ctx)?;
 
-        let engine = Engine::new(feed, fill_rx, strategy, ctx, positions.clone());
-        engine.run().await?;
-        Ok(())
+        let risk = RiskLimits::new(self.settings.risk.clone());
+        let report = Backtester::new(&instance_id, strategy, journal, risk, initial_capital)
+            .run(candles)
+            .await?;
+        let report_path = run_dir.join("report.json");
+        fs::write(&report_path, serde_json::to_vec_pretty(&report)?)?;
+        info!(path = %report_path.display(), "backtest report written");
+        Ok(report)
     }
 
     fn resolve_signer_key(&self) -> AppResult<String> {
@@ -94,10 +297,50 @@ impl App {
     }
 }
 
-fn extract_param_string(cfg: &StrategyInstanceConfig, key: &str, default: &str) -> String {
-    cfg.params
-        .get(key)
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| default.to_string())
+/// Snapshots used to live directly under `snapshot_path`; move them into the
+/// live directory the first time it is used so existing state carries over.
+fn adopt_legacy_snapshots(root: &Path, dir: &Path) -> AppResult<()> {
+    if dir.exists() || !root.is_dir() {
+        return Ok(());
+    }
+    let legacy: Vec<_> = fs::read_dir(root)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    if legacy.is_empty() {
+        return Ok(());
+    }
+    fs::create_dir_all(dir)?;
+    for path in &legacy {
+        if let Some(name) = path.file_name() {
+            fs::rename(path, dir.join(name))?;
+        }
+    }
+    info!(count = legacy.len(), dir = %dir.display(), "moved legacy snapshots");
+    Ok(())
+}
+
+/// Resolves on Ctrl-C, or SIGTERM on unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "unable to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
 }
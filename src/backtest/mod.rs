@@ -0,0 +1,5 @@
+pub mod replay;
+pub mod report;
+
+pub use replay::{Backtester, load_candles};
+pub use report::{BacktestReport, EquityPoint};
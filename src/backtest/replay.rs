@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use rust_decimal::Decimal;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{info, instrument};
+
+use crate::backtest::report::{BacktestReport, EquityPoint};
+use crate::engine::risk::RiskLimits;
+use crate::engine::runner::dispatch;
+use crate::engine::scheduler::Scheduler;
+use crate::errors::{AppError, AppResult};
+use crate::exchange::{FillEvent, OrderManager, PositionManager, SimulatedVenue};
+use crate::marketdata::events::{CandleEvent, MarketEvent};
+use crate::marketdata::order_book::OrderBooks;
+use crate::storage::journal::Journal;
+use crate::strategies::{Strategy, StrategyContext, StrategyResponse};
+use crate::utils::time::{Clock, ManualClock};
+
+pub struct Backtester {
+    strategy: Box<dyn Strategy>,
+    ctx: StrategyContext,
+    venue: Arc<SimulatedVenue>,
+    fills: UnboundedReceiver<FillEvent>,
+    positions: Arc<PositionManager>,
+    orders: Arc<OrderManager>,
+    risk: RiskLimits,
+    clock: ManualClock,
+    initial_capital: Decimal,
+    cash: Decimal,
+    fill_log: Vec<FillEvent>,
+    equity_curve: Vec<EquityPoint>,
+}
+
+impl Backtester {
+    pub fn new(
+        instance_id: &str,
+        strategy: Box<dyn Strategy>,
+        journal: Arc<Journal>,
+        risk: RiskLimits,
+        initial_capital: Decimal,
+    ) -> Self {
+        let (venue, fills) = SimulatedVenue::new();
+        let venue = Arc::new(venue);
+        let positions = Arc::new(PositionManager::new());
+        let orders = Arc::new(OrderManager::new());
+        let ctx = StrategyContext::new(
+            instance_id,
+            venue.clone(),
+            positions.clone(),
+            orders.clone(),
+            Arc::new(OrderBooks::new()),
+            journal,
+        );
+        Self {
+            strategy,
+            ctx,
+            venue,
+            fills,
+            positions,
+            orders,
+            risk,
+            clock: ManualClock::default(),
+            initial_capital,
+            cash: initial_capital,
+            fill_log: Vec::new(),
+            equity_curve: Vec::new(),
+        }
+    }
+
+    #[instrument(skip_all, fields(candles = candles.len()))]
+    pub async fn run(mut self, mut candles: Vec<CandleEvent>) -> AppResult<BacktestReport> {
+        candles.sort_by_key(|c| c.timestamp);
+        let interval = candles
+            .first()
+            .map(|c| c.interval.clone())
+            .unwrap_or_default();
+        let mut scheduler = Scheduler::new();
+        if let Some(first) = candles.first() {
+            self.clock.set(first.open_time);
+            for every in self.strategy.intervals() {
+                scheduler.add(0, every, first.open_time);
+            }
+        }
+        info!(strategy = self.strategy.id(), "backtest started");
+
+        for candle in candles {
+            let timestamp = candle.timestamp;
+            self.clock.set(timestamp);
+            if !scheduler.due(self.clock.now()).is_empty() {
+                let resp = self
+                    .strategy
+                    .on_interval(&mut self.ctx, self.clock.now())
+                    .await?;
+                self.submit(resp).await?;
+                self.drain_fills().await?;
+            }
+            self.positions.update_mark(&candle.asset, candle.close);
+            let event = MarketEvent::Candle(candle);
+            self.venue.on_market_event(&event);
+            self.risk.observe(&event);
+            self.drain_fills().await?;
+
+            let resp = self.strategy.on_event(&mut self.ctx, event).await?;
+            self.submit(resp).await?;
+            self.drain_fills().await?;
+
+            self.equity_curve.push(EquityPoint {
+                timestamp,
+                equity: self.equity(),
+            });
+        }
+
+        let report = BacktestReport::build(
+            self.strategy.id(),
+            &interval,
+            self.initial_capital,
+            self.fill_log,
+            self.equity_curve,
+        );
+        info!(
+            pnl = %report.pnl,
+            max_drawdown = report.max_drawdown,
+            sharpe = report.sharpe,
+            trades = report.trade_count,
+            "backtest finished"
+        );
+        Ok(report)
+    }
+
+    async fn submit(&mut self, resp: StrategyResponse) -> AppResult<()> {
+        dispatch(&self.ctx, &self.risk, &self.positions, resp, false).await
+    }
+
+    async fn drain_fills(&mut self) -> AppResult<()> {
+        while let Ok(fill) = self.fills.try_recv() {
+            let notional = fill.price * fill.size;
+            self.cash += if fill.is_buy { -notional } else { notional };
+            self.cash -= fill.fee;
+            self.positions.apply_fill(&fill);
+            if let Some(order) = self.orders.apply_fill(&fill) {
+                self.ctx.on_order_fill(&order).await?;
+            }
+            self.fill_log.push(fill.clone());
+            let resp = self.strategy.on_fill(&mut self.ctx, fill).await?;
+            self.submit(resp).await?;
+        }
+        Ok(())
+    }
+
+    fn equity(&self) -> Decimal {
+        let exposure: Decimal = self
+            .positions
+            .snapshot()
+            .iter()
+            .filter_map(|p| self.positions.mark(&p.asset).map(|px| p.size * px))
+            .sum();
+        self.cash + exposure
+    }
+}
+
+/// Reads candles stored one JSON-encoded `CandleEvent` per line.
+pub fn load_candles(path: impl AsRef<Path>) -> AppResult<Vec<CandleEvent>> {
+    let path = path.as_ref();
+    let raw = fs::read_to_string(path)?;
+    raw.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            serde_json::from_str(line).map_err(|e| {
+                AppError::Config(format!(
+                    "{}:{}: invalid candle: {e}",
+                    path.display(),
+                    idx + 1
+                ))
+            })
+        })
+        .collect()
+}
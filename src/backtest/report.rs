@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use serde::Serialize;
+
+use crate::exchange::FillEvent;
+use crate::utils::math::{max_drawdown, sharpe_ratio};
+use crate::utils::time::interval_to_millis;
+
+const MILLIS_PER_YEAR: f64 = 365.0 * 86_400_000.0;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EquityPoint {
+    pub timestamp: DateTime<Utc>,
+    pub equity: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    pub strategy: String,
+    pub candles: usize,
+    pub initial_capital: Decimal,
+    pub final_equity: Decimal,
+    pub pnl: Decimal,
+    pub max_drawdown: f64,
+    pub sharpe: f64,
+    /// Round trips: positions taken to flat, or flipped through it.
+    pub trade_count: usize,
+    pub fills: Vec<FillEvent>,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+impl BacktestReport {
+    pub fn build(
+        strategy: &str,
+        interval: &str,
+        initial_capital: Decimal,
+        fills: Vec<FillEvent>,
+        equity_curve: Vec<EquityPoint>,
+    ) -> Self {
+        // drawdown and Sharpe are ratios, so float precision is fine there
+        let equity: Vec<f64> = equity_curve
+            .iter()
+            .filter_map(|p| p.equity.to_f64())
+            .collect();
+        let returns: Vec<f64> = equity
+            .windows(2)
+            .filter(|w| w[0] != 0.0)
+            .map(|w| (w[1] - w[0]) / w[0])
+            .collect();
+        let periods_per_year = interval_to_millis(interval)
+            .map(|ms| MILLIS_PER_YEAR / ms as f64)
+            .unwrap_or(1.0);
+        let final_equity = equity_curve
+            .last()
+            .map(|p| p.equity)
+            .unwrap_or(initial_capital);
+
+        Self {
+            strategy: strategy.to_string(),
+            candles: equity_curve.len(),
+            initial_capital,
+            final_equity,
+            pnl: final_equity - initial_capital,
+            max_drawdown: max_drawdown(&equity),
+            sharpe: sharpe_ratio(&returns, periods_per_year),
+            trade_count: closed_trades(&fills),
+            fills,
+            equity_curve,
+        }
+    }
+}
+
+fn closed_trades(fills: &[FillEvent]) -> usize {
+    let mut positions: HashMap<&str, Decimal> = HashMap::new();
+    let mut closed = 0;
+    for fill in fills {
+        let position = positions.entry(fill.asset.as_str()).or_default();
+        let before = *position;
+        *position += if fill.is_buy { fill.size } else { -fill.size };
+        if !before.is_zero()
+            && (position.is_zero() || position.is_sign_positive() != before.is_sign_positive())
+        {
+            closed += 1;
+        }
+    }
+    closed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(is_buy: bool, size: i64) -> FillEvent {
+        FillEvent {
+            asset: "BTC".into(),
+            price: Decimal::from(100),
+            size: Decimal::from(size),
+            is_buy,
+            cloid: None,
+            fee: Decimal::ZERO,
+            tid: None,
+        }
+    }
+
+    #[test]
+    fn counts_round_trips_not_fills() {
+        // scale in, close, then flip through flat and close again
+        let fills = [
+            fill(true, 1),
+            fill(true, 1),
+            fill(false, 2),
+            fill(false, 1),
+            fill(true, 2),
+            fill(false, 1),
+        ];
+        assert_eq!(closed_trades(&fills), 3);
+    }
+}
@@ -1,8 +1,13 @@
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use hyperliquid_rust_sdk::BaseUrl;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
 use crate::errors::{AppError, AppResult};
+use crate::strategies::AlertSeverity;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TelemetryConfig {
@@ -27,11 +32,32 @@ impl Default for TelemetryConfig {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    #[default]
+    Live,
+    Paper,
+}
+
+impl ExecutionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionMode::Live => "live",
+            ExecutionMode::Paper => "paper",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExchangeConfig {
     #[serde(default = "ExchangeConfig::default_network")]
     pub network: String,
     #[serde(default)]
+    pub mode: ExecutionMode,
+    /// REST request weight budget shared by info calls and order actions;
+    /// Hyperliquid allows 1200 per minute per IP. Zero disables limiting.
+    #[serde(default)]
     pub rate_limit_per_minute: u32,
     pub api_key: Option<String>,
     pub secret_key: Option<String>,
@@ -45,12 +71,21 @@ impl ExchangeConfig {
     fn default_network() -> String {
         "mainnet".to_string()
     }
+
+    pub fn base_url(&self) -> BaseUrl {
+        match self.network.to_lowercase().as_str() {
+            "testnet" => BaseUrl::Testnet,
+            "local" => BaseUrl::Localhost,
+            _ => BaseUrl::Mainnet,
+        }
+    }
 }
 
 impl Default for ExchangeConfig {
     fn default() -> Self {
         Self {
             network: Self::default_network(),
+            mode: ExecutionMode::default(),
             rate_limit_per_minute: 600,
             api_key: None,
             secret_key: None,
@@ -68,6 +103,9 @@ pub struct PersistenceConfig {
     pub journal_path: String,
     #[serde(default)]
     pub snapshot_interval_secs: u64,
+    /// Older snapshot generations kept per key for fallback.
+    #[serde(default = "PersistenceConfig::default_snapshot_history")]
+    pub snapshot_history: usize,
 }
 
 impl PersistenceConfig {
@@ -78,6 +116,19 @@ impl PersistenceConfig {
     fn default_journal_path() -> String {
         "data/journal.log".into()
     }
+
+    fn default_snapshot_history() -> usize {
+        3
+    }
+
+    /// Each execution mode keeps its own snapshots under `snapshot_path`.
+    pub fn snapshot_dir(&self, mode: ExecutionMode) -> PathBuf {
+        Path::new(&self.snapshot_path).join(mode.as_str())
+    }
+
+    pub fn snapshot_interval(&self) -> Option<Duration> {
+        (self.snapshot_interval_secs > 0).then(|| Duration::from_secs(self.snapshot_interval_secs))
+    }
 }
 
 impl Default for PersistenceConfig {
@@ -86,6 +137,185 @@ impl Default for PersistenceConfig {
             snapshot_path: Self::default_path(),
             journal_path: Self::default_journal_path(),
             snapshot_interval_secs: 30,
+            snapshot_history: Self::default_snapshot_history(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RiskConfig {
+    #[serde(default)]
+    pub max_position: HashMap<String, Decimal>,
+    #[serde(default)]
+    pub default_max_position: Option<Decimal>,
+    #[serde(default)]
+    pub max_order_notional: Option<Decimal>,
+    #[serde(default)]
+    pub max_open_orders: Option<usize>,
+    #[serde(default)]
+    pub price_band_bps: Option<u32>,
+    #[serde(default)]
+    pub max_gross_exposure: Option<Decimal>,
+}
+
+impl RiskConfig {
+    pub fn max_position_for(&self, asset: &str) -> Option<Decimal> {
+        self.max_position
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(asset))
+            .map(|(_, limit)| *limit)
+            .or(self.default_max_position)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillSwitchConfig {
+    #[serde(default)]
+    pub max_daily_loss: Option<Decimal>,
+    #[serde(default)]
+    pub max_drawdown: Option<Decimal>,
+    #[serde(default)]
+    pub flatten_on_trip: bool,
+    #[serde(default = "KillSwitchConfig::default_flatten_slippage_bps")]
+    pub flatten_slippage_bps: u32,
+}
+
+impl KillSwitchConfig {
+    fn default_flatten_slippage_bps() -> u32 {
+        50
+    }
+
+    pub fn is_armed(&self) -> bool {
+        self.max_daily_loss.is_some() || self.max_drawdown.is_some()
+    }
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            max_daily_loss: None,
+            max_drawdown: None,
+            flatten_on_trip: false,
+            flatten_slippage_bps: Self::default_flatten_slippage_bps(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReconcilePolicy {
+    #[default]
+    Warn,
+    Resync,
+    /// Trips the kill switch, which stays tripped across restarts.
+    Halt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationConfig {
+    #[serde(default)]
+    pub policy: ReconcilePolicy,
+    #[serde(default = "ReconciliationConfig::default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "ReconciliationConfig::default_size_tolerance")]
+    pub size_tolerance: Decimal,
+}
+
+impl ReconciliationConfig {
+    fn default_interval_secs() -> u64 {
+        60
+    }
+
+    fn default_size_tolerance() -> Decimal {
+        Decimal::new(1, 9)
+    }
+}
+
+impl Default for ReconciliationConfig {
+    fn default() -> Self {
+        Self {
+            policy: ReconcilePolicy::default(),
+            interval_secs: Self::default_interval_secs(),
+            size_tolerance: Self::default_size_tolerance(),
+        }
+    }
+}
+
+/// What happens to resting orders and open positions on SIGINT/SIGTERM. A
+/// second signal exits at once: state is already saved by then, but orders
+/// are left as they are and fills arriving during shutdown are not recorded.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ShutdownConfig {
+    /// Cancels this process's resting orders; reduce-only and trigger orders
+    /// protecting positions are kept unless `flatten` is also set.
+    #[serde(default)]
+    pub cancel_orders: bool,
+    #[serde(default)]
+    pub flatten: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertSinkKind {
+    Log,
+    Journal,
+    /// POSTs each alert as JSON.
+    Webhook {
+        url: String,
+        #[serde(default = "AlertSinkKind::default_timeout_secs")]
+        timeout_secs: u64,
+    },
+    /// Runs `program` with the alert in `SNIVY_ALERT_*` environment variables.
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default = "AlertSinkKind::default_timeout_secs")]
+        timeout_secs: u64,
+    },
+}
+
+impl AlertSinkKind {
+    fn default_timeout_secs() -> u64 {
+        10
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertSinkConfig {
+    #[serde(flatten)]
+    pub kind: AlertSinkKind,
+    #[serde(default)]
+    pub min_severity: AlertSeverity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertsConfig {
+    /// Per instance; alerts past the limit are dropped. Zero disables it.
+    #[serde(default = "AlertsConfig::default_max_per_minute")]
+    pub max_per_minute: u32,
+    #[serde(default = "AlertsConfig::default_sinks")]
+    pub sinks: Vec<AlertSinkConfig>,
+}
+
+impl AlertsConfig {
+    fn default_max_per_minute() -> u32 {
+        10
+    }
+
+    fn default_sinks() -> Vec<AlertSinkConfig> {
+        vec![AlertSinkConfig {
+            kind: AlertSinkKind::Log,
+            min_severity: AlertSeverity::Info,
+        }]
+    }
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            max_per_minute: Self::default_max_per_minute(),
+            sinks: Self::default_sinks(),
         }
     }
 }
@@ -94,11 +324,19 @@ impl Default for PersistenceConfig {
 pub struct StrategyInstanceConfig {
     pub id: String,
     #[serde(default)]
+    pub instance_id: Option<String>,
+    #[serde(default)]
     pub enabled: bool,
     #[serde(default)]
     pub params: serde_json::Value,
 }
 
+impl StrategyInstanceConfig {
+    pub fn instance_id(&self) -> String {
+        self.instance_id.clone().unwrap_or_else(|| self.id.clone())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Settings {
     #[serde(default)]
@@ -108,6 +346,16 @@ pub struct Settings {
     #[serde(default)]
     pub persistence: PersistenceConfig,
     #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub kill_switch: KillSwitchConfig,
+    #[serde(default)]
+    pub reconciliation: ReconciliationConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
     pub strategies: Vec<StrategyInstanceConfig>,
 }
 
@@ -126,4 +374,21 @@ impl Settings {
             .find(|s| s.enabled)
             .ok_or_else(|| AppError::Config("no enabled strategy found".into()))
     }
+
+    pub fn enabled_strategies(&self) -> AppResult<Vec<&StrategyInstanceConfig>> {
+        let enabled: Vec<_> = self.strategies.iter().filter(|s| s.enabled).collect();
+        if enabled.is_empty() {
+            return Err(AppError::Config("no enabled strategy found".into()));
+        }
+        let mut seen = HashSet::new();
+        for cfg in &enabled {
+            let instance_id = cfg.instance_id();
+            if !seen.insert(instance_id.clone()) {
+                return Err(AppError::Config(format!(
+                    "duplicate strategy instance id '{instance_id}'"
+                )));
+            }
+        }
+        Ok(enabled)
+    }
 }
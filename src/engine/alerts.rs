@@ -0,0 +1,341 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+use crate::config::{AlertSinkKind, AlertsConfig};
+use crate::errors::{AppError, AppResult};
+use crate::storage::journal::Journal;
+use crate::strategies::{AlertSeverity, StrategyAction};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub instance_id: String,
+    pub severity: AlertSeverity,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    async fn send(&self, alert: &Alert) -> AppResult<()>;
+}
+
+pub struct LogSink;
+
+#[async_trait]
+impl AlertSink for LogSink {
+    async fn send(&self, alert: &Alert) -> AppResult<()> {
+        match alert.severity {
+            AlertSeverity::Info => {
+                info!(instance = %alert.instance_id, message = %alert.message, "strategy alert")
+            }
+            AlertSeverity::Warning => {
+                warn!(instance = %alert.instance_id, message = %alert.message, "strategy alert")
+            }
+            AlertSeverity::Critical => {
+                error!(instance = %alert.instance_id, message = %alert.message, "strategy alert")
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct JournalSink {
+    journal: Arc<Journal>,
+}
+
+impl JournalSink {
+    pub fn new(journal: Arc<Journal>) -> Self {
+        Self { journal }
+    }
+}
+
+#[async_trait]
+impl AlertSink for JournalSink {
+    async fn send(&self, alert: &Alert) -> AppResult<()> {
+        self.journal.append(&serde_json::json!({
+            "type": "alert",
+            "alert": alert,
+        }))?;
+        Ok(())
+    }
+}
+
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, timeout: Duration) -> AppResult<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| AppError::Config(format!("alert webhook client: {e}")))?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    async fn send(&self, alert: &Alert) -> AppResult<()> {
+        self.client
+            .post(&self.url)
+            .json(alert)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| AppError::Other(format!("alert webhook: {e}")))?;
+        Ok(())
+    }
+}
+
+pub struct CommandSink {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl CommandSink {
+    pub fn new(program: &str, args: &[String], timeout: Duration) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.to_vec(),
+            timeout,
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for CommandSink {
+    async fn send(&self, alert: &Alert) -> AppResult<()> {
+        let status = Command::new(&self.program)
+            .args(&self.args)
+            .env("SNIVY_ALERT_INSTANCE", &alert.instance_id)
+            .env("SNIVY_ALERT_SEVERITY", alert.severity.as_str())
+            .env("SNIVY_ALERT_MESSAGE", &alert.message)
+            .env("SNIVY_ALERT_TIMESTAMP", alert.timestamp.to_rfc3339())
+            .kill_on_drop(true)
+            .status();
+        let status = tokio::time::timeout(self.timeout, status)
+            .await
+            .map_err(|_| AppError::Other(format!("alert command {} timed out", self.program)))??;
+        if !status.success() {
+            return Err(AppError::Other(format!(
+                "alert command {} exited with {status}",
+                self.program
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Fans strategy alerts out to every sink whose minimum severity they meet.
+/// Sends run on their own tasks so a slow sink never stalls the engine loop;
+/// `drain` waits for the ones still in flight at shutdown. The rate limit is
+/// per instance and severity, critical included.
+pub struct AlertDispatcher {
+    sinks: Vec<(AlertSeverity, Arc<dyn AlertSink>)>,
+    max_per_minute: u32,
+    sent: HashMap<(String, AlertSeverity), VecDeque<Instant>>,
+    keyed: HashMap<(String, String), Instant>,
+    pending: Vec<JoinHandle<()>>,
+}
+
+impl Default for AlertDispatcher {
+    /// Logs every alert, unthrottled.
+    fn default() -> Self {
+        Self::new(0).with_sink(AlertSeverity::Info, Arc::new(LogSink))
+    }
+}
+
+impl AlertDispatcher {
+    pub fn new(max_per_minute: u32) -> Self {
+        Self {
+            sinks: Vec::new(),
+            max_per_minute,
+            sent: HashMap::new(),
+            keyed: HashMap::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &AlertsConfig, journal: Arc<Journal>) -> AppResult<Self> {
+        let mut dispatcher = Self::new(config.max_per_minute);
+        for sink in &config.sinks {
+            let built: Arc<dyn AlertSink> = match &sink.kind {
+                AlertSinkKind::Log => Arc::new(LogSink),
+                AlertSinkKind::Journal => Arc::new(JournalSink::new(journal.clone())),
+                AlertSinkKind::Webhook { url, timeout_secs } => {
+                    Arc::new(WebhookSink::new(url, Duration::from_secs(*timeout_secs))?)
+                }
+                AlertSinkKind::Command {
+                    program,
+                    args,
+                    timeout_secs,
+                } => Arc::new(CommandSink::new(
+                    program,
+                    args,
+                    Duration::from_secs(*timeout_secs),
+                )),
+            };
+            dispatcher = dispatcher.with_sink(sink.min_severity, built);
+        }
+        Ok(dispatcher)
+    }
+
+    pub fn with_sink(mut self, min_severity: AlertSeverity, sink: Arc<dyn AlertSink>) -> Self {
+        self.sinks.push((min_severity, sink));
+        self
+    }
+
+    pub fn notify(
+        &mut self,
+        instance_id: &str,
+        actions: Vec<StrategyAction>,
+        timestamp: DateTime<Utc>,
+    ) {
+        self.pending.retain(|handle| !handle.is_finished());
+        for action in actions {
+            let StrategyAction::Alert { severity, message } = action else {
+                continue;
+            };
+            if !self.allow(instance_id, severity) {
+                warn!(instance = instance_id, ?severity, %message, "alert rate limit hit, dropping");
+                continue;
+            }
+            let alert = Arc::new(Alert {
+                instance_id: instance_id.to_string(),
+                severity,
+                message,
+                timestamp,
+            });
+            for (min_severity, sink) in &self.sinks {
+                if severity < *min_severity {
+                    continue;
+                }
+                let (sink, alert) = (sink.clone(), alert.clone());
+                self.pending.push(tokio::spawn(async move {
+                    if let Err(e) = sink.send(&alert).await {
+                        warn!(instance = %alert.instance_id, error = %e, "alert delivery failed");
+                    }
+                }));
+            }
+        }
+    }
+
+    /// Like `notify`, but drops the alert if one with the same `key` went out
+    /// for this instance within the rate window, so a step that fails on every
+    /// tick alerts once a minute rather than once a tick.
+    pub fn notify_once(
+        &mut self,
+        instance_id: &str,
+        key: &str,
+        action: StrategyAction,
+        timestamp: DateTime<Utc>,
+    ) {
+        let now = Instant::now();
+        self.keyed
+            .retain(|_, at| now.duration_since(*at) <= RATE_WINDOW);
+        let entry = (instance_id.to_string(), key.to_string());
+        if self.keyed.contains_key(&entry) {
+            return;
+        }
+        self.keyed.insert(entry, now);
+        self.notify(instance_id, vec![action], timestamp);
+    }
+
+    /// Waits up to `timeout` for in-flight sends, then abandons the rest.
+    pub async fn drain(&mut self, timeout: Duration) {
+        let pending = std::mem::take(&mut self.pending);
+        if pending.is_empty() {
+            return;
+        }
+        let count = pending.len();
+        if tokio::time::timeout(timeout, futures::future::join_all(pending))
+            .await
+            .is_err()
+        {
+            warn!(count, "timed out delivering alerts at shutdown");
+        }
+    }
+
+    fn allow(&mut self, instance_id: &str, severity: AlertSeverity) -> bool {
+        if self.max_per_minute == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        let sent = self
+            .sent
+            .entry((instance_id.to_string(), severity))
+            .or_default();
+        while sent
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > RATE_WINDOW)
+        {
+            sent.pop_front();
+        }
+        if sent.len() >= self.max_per_minute as usize {
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingSink(AtomicUsize);
+
+    #[async_trait]
+    impl AlertSink for CountingSink {
+        async fn send(&self, _alert: &Alert) -> AppResult<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    fn critical(message: &str) -> StrategyAction {
+        StrategyAction::alert(AlertSeverity::Critical, message.to_string())
+    }
+
+    #[tokio::test]
+    async fn critical_alerts_are_rate_limited() {
+        let sink = Arc::new(CountingSink::default());
+        let mut alerts = AlertDispatcher::new(2).with_sink(AlertSeverity::Info, sink.clone());
+        for _ in 0..5 {
+            alerts.notify("btc", vec![critical("down")], Utc::now());
+        }
+        alerts.drain(Duration::from_secs(1)).await;
+        assert_eq!(sink.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn repeated_keyed_alert_goes_out_once() {
+        let sink = Arc::new(CountingSink::default());
+        let mut alerts = AlertDispatcher::new(0).with_sink(AlertSeverity::Info, sink.clone());
+        for _ in 0..3 {
+            alerts.notify_once("btc", "dispatch", critical("dispatch failed"), Utc::now());
+        }
+        alerts.notify_once("btc", "bracket", critical("bracket failed"), Utc::now());
+        alerts.notify_once("eth", "dispatch", critical("dispatch failed"), Utc::now());
+        alerts.drain(Duration::from_secs(1)).await;
+        assert_eq!(sink.0.load(Ordering::SeqCst), 3);
+    }
+}
@@ -0,0 +1,316 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::config::KillSwitchConfig;
+use crate::errors::AppResult;
+use crate::exchange::order_router::{OrderIntent, OrderSide, OrderTif};
+use crate::exchange::{
+    ExecutionVenue, FillEvent, OpenOrder, OrderManager, OrderRef, PositionManager,
+};
+use crate::storage::journal::Journal;
+use crate::storage::persistence::SnapshotStore;
+use crate::utils::math::bps;
+use crate::utils::time::now;
+
+const SNAPSHOT_KEY: &str = "kill_switch";
+const PERSIST_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KillSwitchState {
+    pub tripped: bool,
+    pub reason: Option<String>,
+    pub tripped_at: Option<DateTime<Utc>>,
+    pub day: NaiveDate,
+    pub day_start_pnl: Decimal,
+    pub peak_pnl: Decimal,
+    pub cash: Decimal,
+    #[serde(default)]
+    pub baselined: bool,
+}
+
+impl KillSwitchState {
+    fn fresh() -> Self {
+        Self {
+            tripped: false,
+            reason: None,
+            tripped_at: None,
+            day: now().date_naive(),
+            day_start_pnl: Decimal::ZERO,
+            peak_pnl: Decimal::ZERO,
+            cash: Decimal::ZERO,
+            baselined: false,
+        }
+    }
+}
+
+/// Halts trading once the daily loss or peak-to-trough drawdown limit is hit.
+/// The tripped flag is persisted and only cleared by deleting the snapshot.
+pub struct KillSwitch {
+    config: KillSwitchConfig,
+    state: KillSwitchState,
+    store: SnapshotStore,
+    venue: Arc<dyn ExecutionVenue>,
+    journal: Arc<Journal>,
+    last_persist: Instant,
+    dirty: bool,
+}
+
+impl KillSwitch {
+    pub fn new(
+        config: KillSwitchConfig,
+        store: SnapshotStore,
+        venue: Arc<dyn ExecutionVenue>,
+        journal: Arc<Journal>,
+    ) -> AppResult<Self> {
+        let state = store
+            .load::<KillSwitchState>(SNAPSHOT_KEY)?
+            .unwrap_or_else(KillSwitchState::fresh);
+        if state.tripped {
+            warn!(
+                reason = state.reason.as_deref().unwrap_or_default(),
+                "kill switch restored in tripped state, delete the {SNAPSHOT_KEY} snapshot to re-arm"
+            );
+        }
+        Ok(Self {
+            config,
+            state,
+            store,
+            venue,
+            journal,
+            last_persist: Instant::now(),
+            dirty: false,
+        })
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.state.tripped
+    }
+
+    pub fn on_fill(&mut self, fill: &FillEvent) -> AppResult<()> {
+        let notional = fill.price * fill.size;
+        self.state.cash += if fill.is_buy { -notional } else { notional };
+        self.state.cash -= fill.fee;
+        self.persist()
+    }
+
+    /// Realized plus unrealized PnL: cash flow from fills plus the marked value
+    /// of every open position.
+    pub fn pnl(&self, positions: &PositionManager) -> Decimal {
+        let marked: Decimal = positions
+            .snapshot()
+            .iter()
+            .map(|p| {
+                let mark = positions.mark(&p.asset).unwrap_or(p.entry_price);
+                p.size * mark
+            })
+            .sum();
+        self.state.cash + marked
+    }
+
+    /// Returns true only on the call that trips the switch.
+    pub fn evaluate(&mut self, positions: &PositionManager) -> AppResult<bool> {
+        if self.state.tripped || !self.config.is_armed() {
+            return Ok(false);
+        }
+
+        let pnl = self.pnl(positions);
+        // positions seeded from the exchange carry value the switch never paid
+        // for, so the first evaluation sets the reference points
+        if !self.state.baselined {
+            self.state.day_start_pnl = pnl;
+            self.state.peak_pnl = pnl;
+            self.state.baselined = true;
+            self.persist()?;
+        }
+        let today = now().date_naive();
+        if today != self.state.day {
+            self.state.day = today;
+            self.state.day_start_pnl = pnl;
+            self.persist()?;
+        }
+        if pnl > self.state.peak_pnl {
+            self.state.peak_pnl = pnl;
+            self.dirty = true;
+        }
+
+        let daily_loss = self.state.day_start_pnl - pnl;
+        let drawdown = self.state.peak_pnl - pnl;
+        let reason = match (self.config.max_daily_loss, self.config.max_drawdown) {
+            (Some(limit), _) if daily_loss >= limit => {
+                Some(format!("daily loss {daily_loss:.2} breached limit {limit}"))
+            }
+            (_, Some(limit)) if drawdown >= limit => {
+                Some(format!("drawdown {drawdown:.2} breached limit {limit}"))
+            }
+            _ => None,
+        };
+
+        let Some(reason) = reason else {
+            if self.dirty && self.last_persist.elapsed() >= PERSIST_INTERVAL {
+                self.persist()?;
+            }
+            return Ok(false);
+        };
+
+        error!(%pnl, reason = %reason, "kill switch tripped, halting all strategies");
+        let record = serde_json::json!({
+            "type": "kill_switch_tripped",
+            "reason": reason,
+            "pnl": pnl,
+            "peak_pnl": self.state.peak_pnl,
+            "day_start_pnl": self.state.day_start_pnl,
+        });
+        self.trip(reason, record)?;
+        Ok(true)
+    }
+
+    /// Trips the switch for a reason outside its own limits, e.g. drift found
+    /// by the reconciler under the halt policy. Works whether or not any limit
+    /// is configured.
+    pub fn halt(&mut self, reason: &str) -> AppResult<()> {
+        if self.state.tripped {
+            return Ok(());
+        }
+        error!(reason, "kill switch tripped, halting all strategies");
+        let record = serde_json::json!({
+            "type": "kill_switch_tripped",
+            "reason": reason,
+        });
+        self.trip(reason.to_string(), record)
+    }
+
+    /// Books a position change forced by the reconciler as if it traded at
+    /// `price`, so a resync moves neither the daily loss nor the drawdown.
+    pub fn on_resync(&mut self, delta: Decimal, price: Decimal) -> AppResult<()> {
+        self.state.cash -= delta * price;
+        self.persist()
+    }
+
+    fn trip(&mut self, reason: String, record: serde_json::Value) -> AppResult<()> {
+        self.state.tripped = true;
+        self.state.tripped_at = Some(now());
+        self.state.reason = Some(reason);
+        self.persist()?;
+        if let Err(e) = self.journal.append(&record) {
+            warn!(error = %e, "failed to journal kill switch trip");
+        }
+        Ok(())
+    }
+
+    /// Cancels resting orders and, when configured, flattens positions with
+    /// reduce-only IOC orders. Failures are logged, not propagated, so one bad
+    /// cancel does not stop the rest.
+    pub async fn enforce(&self, positions: &PositionManager, orders: &OrderManager) {
+        self.cancel_open_orders(orders, !self.config.flatten_on_trip)
+            .await;
+        if self.config.flatten_on_trip {
+            self.flatten(positions, "kill_switch_flatten").await;
+        }
+    }
+
+    /// Cancels the resting orders this process placed. Reduce-only and trigger
+    /// orders protect open positions, so they stay when `keep_protective` is
+    /// set, i.e. whenever the positions are not being flattened too.
+    pub async fn cancel_open_orders(&self, orders: &OrderManager, keep_protective: bool) {
+        let open = match self.venue.open_orders().await {
+            Ok(open) => open,
+            Err(e) => {
+                warn!(error = %e, "could not list open orders");
+                return;
+            }
+        };
+        let targets: Vec<(OpenOrder, String)> = open
+            .into_iter()
+            .filter_map(|order| {
+                let tracked = order
+                    .cloid
+                    .as_deref()
+                    .and_then(|cloid| orders.get(cloid))
+                    .or_else(|| orders.resolve(&OrderRef::Oid(order.oid)))?;
+                if keep_protective && (tracked.reduce_only || tracked.trigger.is_some()) {
+                    return None;
+                }
+                Some((order, tracked.cloid))
+            })
+            .collect();
+        if targets.is_empty() {
+            return;
+        }
+        let requests = targets
+            .iter()
+            .map(|(order, _)| (order.asset.clone(), OrderRef::Oid(order.oid)))
+            .collect();
+        match self.venue.cancel_batch(requests).await {
+            Ok(results) => {
+                for ((order, cloid), result) in targets.into_iter().zip(results) {
+                    match result {
+                        Ok(()) => orders.mark_cancelled(&cloid),
+                        Err(e) => warn!(oid = order.oid, error = %e, "open order cancel failed"),
+                    }
+                }
+            }
+            Err(e) => warn!(error = %e, "open order cancel batch failed"),
+        }
+    }
+
+    /// Closes every position from its mark with reduce-only IOC orders tagged
+    /// `tag`.
+    pub async fn flatten(&self, positions: &PositionManager, tag: &str) {
+        for position in positions.snapshot() {
+            if !position.size.is_zero() {
+                self.close(positions, &position.asset, position.size, tag)
+                    .await;
+            }
+        }
+    }
+
+    /// Closes `size` of a position on `asset` (positive for a long) from its
+    /// mark with a reduce-only IOC order tagged `tag`.
+    pub async fn close(&self, positions: &PositionManager, asset: &str, size: Decimal, tag: &str) {
+        let slippage = bps(self.config.flatten_slippage_bps);
+        let Some(mark) = positions.mark(asset) else {
+            warn!(asset, "no mark price, cannot flatten position");
+            return;
+        };
+        let (side, limit_px) = if size > Decimal::ZERO {
+            (OrderSide::Sell, mark * (Decimal::ONE - slippage))
+        } else {
+            (OrderSide::Buy, mark * (Decimal::ONE + slippage))
+        };
+        let intent = match OrderIntent::new(asset, side, size.abs(), limit_px, OrderTif::Ioc) {
+            Ok(intent) => intent.with_reduce_only(true).with_tag(tag),
+            Err(e) => {
+                warn!(asset, error = %e, "cannot build flatten order");
+                return;
+            }
+        };
+        info!(asset, %size, "flattening position");
+        match self.venue.submit(intent).await {
+            Ok(ack) if ack.is_rejected() => {
+                warn!(asset, status = ?ack.status, "flatten order rejected")
+            }
+            Ok(_) => {}
+            Err(e) => warn!(asset, error = %e, "flatten order failed"),
+        }
+    }
+
+    /// Writes state held back by the persist throttle.
+    pub fn flush(&mut self) -> AppResult<()> {
+        if self.dirty {
+            self.persist()?;
+        }
+        Ok(())
+    }
+
+    fn persist(&mut self) -> AppResult<()> {
+        self.store.save(SNAPSHOT_KEY, &self.state)?;
+        self.last_persist = Instant::now();
+        self.dirty = false;
+        Ok(())
+    }
+}
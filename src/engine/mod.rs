@@ -1,2 +1,5 @@
+pub mod alerts;
+pub mod kill_switch;
 pub mod risk;
 pub mod runner;
+pub mod scheduler;
@@ -1,12 +1,305 @@
-use crate::exchange::order_router::OrderIntent;
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::config::RiskConfig;
+use crate::errors::AppResult;
+use crate::exchange::PositionManager;
+use crate::exchange::order_router::{OrderIntent, OrderSide, OrderTif};
+use crate::marketdata::events::MarketEvent;
+use crate::strategies::StrategyContext;
+use crate::utils::math::bps;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskRejectCode {
+    InvalidOrder,
+    MaxPosition,
+    MaxOrderNotional,
+    MaxOpenOrders,
+    PriceBand,
+    NoReferencePrice,
+    GrossExposure,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskRejection {
+    pub code: RiskRejectCode,
+    pub reason: String,
+}
+
+impl RiskRejection {
+    fn new(code: RiskRejectCode, reason: String) -> Self {
+        Self { code, reason }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct RiskLimits {
-    pub max_position: f64,
+    config: RiskConfig,
+    mids: HashMap<String, Decimal>,
 }
 
 impl RiskLimits {
-    pub fn allow(&self, _intent: &OrderIntent) -> bool {
-        true
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            mids: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, event: &MarketEvent) {
+        if let Some(price) = event.price() {
+            self.mids.insert(event.asset().to_string(), price);
+        }
+    }
+
+    pub fn mid(&self, asset: &str) -> Option<Decimal> {
+        self.mids.get(asset).copied()
+    }
+
+    /// Returns the intents that pass every check; rejections are logged and
+    /// journaled with their reason code.
+    pub fn screen(
+        &self,
+        ctx: &StrategyContext,
+        positions: &PositionManager,
+        intents: Vec<OrderIntent>,
+    ) -> AppResult<Vec<OrderIntent>> {
+        if intents.is_empty() {
+            return Ok(intents);
+        }
+
+        let mut open_orders = ctx.orders_handle().open_count();
+        let marks = Self::position_marks(positions);
+        let mut projected: HashMap<String, Decimal> = positions
+            .snapshot()
+            .into_iter()
+            .map(|p| (p.asset, p.size))
+            .collect();
+
+        let mut accepted = Vec::with_capacity(intents.len());
+        for intent in intents {
+            if let Err(rejection) = self.check(&intent, &projected, &marks, open_orders) {
+                Self::record_rejection(ctx, &intent, &rejection);
+                continue;
+            }
+
+            let delta = match intent.side {
+                OrderSide::Buy => intent.size,
+                OrderSide::Sell => -intent.size,
+            };
+            *projected.entry(intent.asset.clone()).or_default() += delta;
+            if intent.tif != OrderTif::Ioc {
+                open_orders += 1;
+            }
+            accepted.push(intent);
+        }
+        Ok(accepted)
+    }
+
+    /// A modify replaces an order that is already counted as open, so the
+    /// open-order limit is skipped and everything else applies.
+    pub fn screen_modify(
+        &self,
+        ctx: &StrategyContext,
+        positions: &PositionManager,
+        intent: &OrderIntent,
+    ) -> bool {
+        let projected: HashMap<String, Decimal> = positions
+            .snapshot()
+            .into_iter()
+            .map(|p| (p.asset, p.size))
+            .collect();
+        match self.check(intent, &projected, &Self::position_marks(positions), 0) {
+            Ok(()) => true,
+            Err(rejection) => {
+                Self::record_rejection(ctx, intent, &rejection);
+                false
+            }
+        }
+    }
+
+    /// Mark, or entry price when unmarked, of every open position; used to
+    /// value positions in assets no market data has been seen for.
+    fn position_marks(positions: &PositionManager) -> HashMap<String, Decimal> {
+        positions
+            .snapshot()
+            .into_iter()
+            .map(|p| {
+                let price = positions.mark(&p.asset).unwrap_or(p.entry_price);
+                (p.asset, price)
+            })
+            .collect()
+    }
+
+    fn record_rejection(ctx: &StrategyContext, intent: &OrderIntent, rejection: &RiskRejection) {
+        warn!(
+            instance = ctx.instance_id(),
+            code = ?rejection.code,
+            reason = %rejection.reason,
+            intent = %intent.describe(),
+            "intent rejected by risk limits"
+        );
+        let record = serde_json::json!({
+            "type": "risk_rejection",
+            "instance": ctx.instance_id(),
+            "code": rejection.code,
+            "reason": rejection.reason,
+            "intent": intent,
+        });
+        if let Err(e) = ctx.journal().append(&record) {
+            warn!(error = %e, "failed to journal risk rejection");
+        }
+    }
+
+    /// `positions` holds the signed size per asset including intents already
+    /// accepted earlier in the same batch; `marks` prices the ones with no mid.
+    pub fn check(
+        &self,
+        intent: &OrderIntent,
+        positions: &HashMap<String, Decimal>,
+        marks: &HashMap<String, Decimal>,
+        open_orders: usize,
+    ) -> Result<(), RiskRejection> {
+        intent
+            .validate()
+            .map_err(|e| RiskRejection::new(RiskRejectCode::InvalidOrder, e.to_string()))?;
+        let (size, limit_px) = (intent.size, intent.limit_px);
+
+        // untriggered orders are not marketable, so the band does not apply
+        if let Some(band_bps) = self.config.price_band_bps
+            && intent.trigger.is_none()
+        {
+            let mid = self
+                .mid(&intent.asset)
+                .filter(|mid| *mid > Decimal::ZERO)
+                .ok_or_else(|| {
+                    RiskRejection::new(
+                        RiskRejectCode::NoReferencePrice,
+                        format!("no mid known for {}", intent.asset),
+                    )
+                })?;
+            let deviation = (limit_px - mid).abs() / mid;
+            if deviation > bps(band_bps) {
+                return Err(RiskRejection::new(
+                    RiskRejectCode::PriceBand,
+                    format!(
+                        "price {limit_px} is {:.1}bps from mid {mid}",
+                        deviation * Decimal::from(10_000)
+                    ),
+                ));
+            }
+        }
+
+        if let Some(max_open) = self.config.max_open_orders
+            && open_orders >= max_open
+        {
+            return Err(RiskRejection::new(
+                RiskRejectCode::MaxOpenOrders,
+                format!("{open_orders} open orders, limit {max_open}"),
+            ));
+        }
+
+        if intent.reduce_only {
+            return Ok(());
+        }
+
+        let notional = size * limit_px;
+        if let Some(max_notional) = self.config.max_order_notional
+            && notional > max_notional
+        {
+            return Err(RiskRejection::new(
+                RiskRejectCode::MaxOrderNotional,
+                format!("order notional {notional:.2} exceeds {max_notional}"),
+            ));
+        }
+
+        let current = positions.get(&intent.asset).copied().unwrap_or_default();
+        let projected = match intent.side {
+            OrderSide::Buy => current + size,
+            OrderSide::Sell => current - size,
+        };
+        if let Some(max_position) = self.config.max_position_for(&intent.asset)
+            && projected.abs() > max_position
+        {
+            return Err(RiskRejection::new(
+                RiskRejectCode::MaxPosition,
+                format!(
+                    "projected {} position {projected} exceeds {max_position}",
+                    intent.asset
+                ),
+            ));
+        }
+
+        if let Some(max_gross) = self.config.max_gross_exposure {
+            let mut gross = projected.abs() * self.mid(&intent.asset).unwrap_or(limit_px);
+            for (asset, size) in positions {
+                if asset == &intent.asset || size.is_zero() {
+                    continue;
+                }
+                let price = self
+                    .mid(asset)
+                    .or_else(|| marks.get(asset).copied())
+                    .filter(|price| *price > Decimal::ZERO)
+                    .ok_or_else(|| {
+                        RiskRejection::new(
+                            RiskRejectCode::NoReferencePrice,
+                            format!("no price known for the {asset} position"),
+                        )
+                    })?;
+                gross += size.abs() * price;
+            }
+            if gross > max_gross {
+                return Err(RiskRejection::new(
+                    RiskRejectCode::GrossExposure,
+                    format!("gross exposure {gross:.2} would exceed {max_gross}"),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> RiskLimits {
+        RiskLimits::new(RiskConfig {
+            max_gross_exposure: Some(Decimal::from(1_000)),
+            ..RiskConfig::default()
+        })
+    }
+
+    fn buy() -> OrderIntent {
+        OrderIntent::new(
+            "BTC",
+            OrderSide::Buy,
+            Decimal::ONE,
+            Decimal::from(100),
+            OrderTif::Gtc,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn unpriced_position_is_valued_at_its_mark() {
+        let positions = HashMap::from([("ETH".to_string(), Decimal::from(5))]);
+        let marks = HashMap::from([("ETH".to_string(), Decimal::from(200))]);
+        let rejection = limits().check(&buy(), &positions, &marks, 0).unwrap_err();
+        assert_eq!(rejection.code, RiskRejectCode::GrossExposure);
+    }
+
+    #[test]
+    fn position_without_any_price_is_rejected() {
+        let positions = HashMap::from([("ETH".to_string(), Decimal::from(5))]);
+        let rejection = limits()
+            .check(&buy(), &positions, &HashMap::new(), 0)
+            .unwrap_err();
+        assert_eq!(rejection.code, RiskRejectCode::NoReferencePrice);
     }
 }
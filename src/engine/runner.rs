@@ -1,98 +1,861 @@
+use std::collections::VecDeque;
+use std::future;
 use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::sync::{broadcast, mpsc::UnboundedReceiver};
-use tracing::{info, instrument};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+use tokio::time::{Instant, Interval, MissedTickBehavior, interval_at};
+use tokio_stream::StreamExt;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::errors::AppResult;
-use crate::exchange::{FillEvent, PositionManager};
-use crate::marketdata::feeds::FeedCoordinator;
-use crate::strategies::{Strategy, StrategyContext};
+use crate::config::ShutdownConfig;
+use crate::engine::alerts::AlertDispatcher;
+use crate::engine::kill_switch::KillSwitch;
+use crate::engine::risk::RiskLimits;
+use crate::engine::scheduler::Scheduler;
+use crate::errors::{AppError, AppResult};
+use crate::exchange::order_router::{OrderSide, TriggerKind};
+use crate::exchange::position_manager::Position;
+use crate::exchange::reconciler::{PositionDrift, Reconciler};
+use crate::exchange::{FillEvent, OrderManager, OrderUpdateEvent, PositionManager, TrackedOrder};
+use crate::marketdata::events::MarketEvent;
+use crate::marketdata::feeds::{FeedCoordinator, MarketSubscription};
+use crate::marketdata::order_book::OrderBooks;
+use crate::storage::journal::Journal;
+use crate::storage::persistence::{Snapshot, SnapshotStore};
+use crate::strategies::{
+    AlertSeverity, Strategy, StrategyAction, StrategyContext, StrategyResponse,
+};
+use crate::utils::time::{Clock, SystemClock};
+
+const ENGINE_SNAPSHOT_KEY: &str = "engine";
+const ALERT_DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+/// Trade ids remembered to drop fills the exchange delivers more than once.
+const SEEN_FILLS: usize = 1024;
+
+/// Shared account state saved next to each instance's `snapshot_state`.
+#[derive(Debug, Serialize, Deserialize)]
+struct EngineSnapshot {
+    positions: Vec<Position>,
+    orders: Vec<TrackedOrder>,
+    #[serde(default)]
+    seen_tids: VecDeque<u64>,
+}
+
+pub struct StrategySlot {
+    instance_id: String,
+    subscriptions: Vec<MarketSubscription>,
+    intervals: Vec<Duration>,
+    strategy: Box<dyn Strategy>,
+    ctx: StrategyContext,
+}
+
+impl StrategySlot {
+    pub fn new(instance_id: String, strategy: Box<dyn Strategy>, ctx: StrategyContext) -> Self {
+        Self {
+            instance_id,
+            subscriptions: strategy.subscriptions(),
+            intervals: strategy.intervals(),
+            strategy,
+            ctx,
+        }
+    }
+
+    pub fn subscriptions(&self) -> &[MarketSubscription] {
+        &self.subscriptions
+    }
+
+    fn wants(&self, event: &MarketEvent) -> bool {
+        self.subscriptions.iter().any(|s| s.matches(event))
+    }
+}
 
 pub struct Engine {
+    slots: Vec<StrategySlot>,
     feed: FeedCoordinator,
     fills: Option<UnboundedReceiver<FillEvent>>,
-    strategy: Box<dyn Strategy>,
-    ctx: StrategyContext,
+    order_updates: Option<UnboundedReceiver<OrderUpdateEvent>>,
     positions: Arc<PositionManager>,
+    orders: Arc<OrderManager>,
+    books: Arc<OrderBooks>,
+    risk: RiskLimits,
+    kill_switch: KillSwitch,
+    reconciler: Option<Reconciler>,
+    clock: Arc<dyn Clock>,
+    shutdown: Option<oneshot::Receiver<()>>,
+    shutdown_config: ShutdownConfig,
+    snapshots: Option<SnapshotStore>,
+    snapshot_interval: Option<Duration>,
+    journal: Option<Arc<Journal>>,
+    alerts: AlertDispatcher,
+    seen_tids: VecDeque<u64>,
 }
 
 impl Engine {
     pub fn new(
+        slots: Vec<StrategySlot>,
         feed: FeedCoordinator,
-        fills: Option<UnboundedReceiver<FillEvent>>,
-        strategy: Box<dyn Strategy>,
-        ctx: StrategyContext,
         positions: Arc<PositionManager>,
+        orders: Arc<OrderManager>,
+        books: Arc<OrderBooks>,
+        risk: RiskLimits,
+        kill_switch: KillSwitch,
     ) -> Self {
         Self {
+            slots,
             feed,
-            fills,
-            strategy,
-            ctx,
+            fills: None,
+            order_updates: None,
             positions,
+            orders,
+            books,
+            risk,
+            kill_switch,
+            reconciler: None,
+            clock: Arc::new(SystemClock),
+            shutdown: None,
+            shutdown_config: ShutdownConfig::default(),
+            snapshots: None,
+            snapshot_interval: None,
+            journal: None,
+            alerts: AlertDispatcher::default(),
+            seen_tids: VecDeque::new(),
         }
     }
 
+    pub fn with_fills(mut self, fills: UnboundedReceiver<FillEvent>) -> Self {
+        self.fills = Some(fills);
+        self
+    }
+
+    pub fn with_order_updates(mut self, updates: UnboundedReceiver<OrderUpdateEvent>) -> Self {
+        self.order_updates = Some(updates);
+        self
+    }
+
+    pub fn with_reconciler(mut self, reconciler: Reconciler) -> Self {
+        self.reconciler = Some(reconciler);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_alerts(mut self, alerts: AlertDispatcher) -> Self {
+        self.alerts = alerts;
+        self
+    }
+
+    /// Stops the engine once `signal` fires, applying `config` on the way out.
+    pub fn with_shutdown(mut self, signal: oneshot::Receiver<()>, config: ShutdownConfig) -> Self {
+        self.shutdown = Some(signal);
+        self.shutdown_config = config;
+        self
+    }
+
+    /// Restores from `snapshots` on start, saves to it every `interval` and
+    /// once more on shutdown.
+    pub fn with_persistence(
+        mut self,
+        snapshots: SnapshotStore,
+        interval: Option<Duration>,
+        journal: Arc<Journal>,
+    ) -> Self {
+        self.snapshots = Some(snapshots);
+        self.snapshot_interval = interval;
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Runs until the feed ends, a shutdown signal arrives or a handler
+    /// fails; strategies are shut down in every case.
     #[instrument(skip_all)]
     pub async fn run(mut self) -> AppResult<()> {
-        let mut market_stream = self.feed.subscribe();
-        info!("engine started");
+        let result = self.drive().await;
+        if let Err(e) = &result {
+            error!(error = %e, "engine stopped on error");
+        }
+        self.shutdown().await;
+        result
+    }
+
+    async fn drive(&mut self) -> AppResult<()> {
+        self.restore();
+        // positions from the snapshot keep their realized PnL; size and entry
+        // are then overwritten by the exchange
+        if let Some(reconciler) = &self.reconciler {
+            reconciler.seed(&self.positions).await?;
+        }
+        let mut market_stream = BroadcastStream::new(self.feed.subscribe());
+        info!(
+            instances = self.slots.len(),
+            subscriptions = self.feed.subscriptions().len(),
+            "engine started"
+        );
+        if self.kill_switch.is_tripped() {
+            self.kill_switch
+                .enforce(&self.positions, &self.orders)
+                .await;
+        }
+
+        let mut fills = self.fills.take();
+        let mut order_updates = self.order_updates.take();
+        let mut shutdown = self.shutdown.take();
+        let mut reconcile_timer = timer(self.reconciler.as_ref().and_then(|r| r.interval()));
+        let mut snapshot_timer = timer(self.snapshot_interval);
+        let mut scheduler = Scheduler::new();
+        let start = self.clock.now();
+        for (idx, slot) in self.slots.iter().enumerate() {
+            for every in &slot.intervals {
+                scheduler.add(idx, *every, start);
+            }
+        }
 
         loop {
-            if let Some(fill_rx) = self.fills.as_mut() {
-                tokio::select! {
-                    evt = market_stream.recv() => {
-                        if !self.handle_market_event(evt).await? {
-                            break;
-                        }
+            let until_due = scheduler
+                .next_due()
+                .map(|at| (at - self.clock.now()).to_std().unwrap_or_default());
+            tokio::select! {
+                evt = market_stream.next() => {
+                    if !self.handle_market_event(evt).await? {
+                        break;
                     }
-                    fill = fill_rx.recv() => {
-                        if !self.handle_fill(fill).await? {
-                            self.fills = None;
-                        }
+                }
+                fill = next_message(&mut fills) => {
+                    if !self.handle_fill(fill).await? {
+                        fills = None;
+                    }
+                }
+                update = next_message(&mut order_updates) => {
+                    match update {
+                        Some(update) => self.orders.apply_update(&update),
+                        None => order_updates = None,
                     }
                 }
-            } else {
-                let evt = market_stream.recv().await;
-                if !self.handle_market_event(evt).await? {
-                    break;
+                signal = next_signal(&mut shutdown) => {
+                    if signal {
+                        info!("shutdown requested");
+                        break;
+                    }
+                    shutdown = None;
+                }
+                _ = sleep_for(until_due) => {
+                    self.handle_intervals(&mut scheduler).await?;
+                }
+                _ = next_tick(&mut snapshot_timer) => self.save_snapshots(),
+                _ = next_tick(&mut reconcile_timer) => {
+                    if let Some(reconciler) = &self.reconciler {
+                        let result = reconciler.reconcile(&self.positions, &self.orders).await;
+                        let settled = settle_reconcile(
+                            result,
+                            &mut self.kill_switch,
+                            &self.positions,
+                            &self.orders,
+                        )
+                        .await;
+                        if let Err(e) = settled {
+                            self.report("reconciler", "reconcile", &e);
+                        }
+                    }
                 }
             }
         }
         Ok(())
     }
 
+    /// Best effort: every step runs even if an earlier one failed.
+    /// State is saved before the steps that wait on the exchange, so a second
+    /// signal that force-exits mid-shutdown still leaves a current snapshot.
+    async fn shutdown(&mut self) {
+        info!("engine shutting down");
+        self.feed.stop();
+        self.persist_state();
+        for slot in &mut self.slots {
+            if let Err(e) = slot.strategy.shutdown(&mut slot.ctx).await {
+                warn!(instance = %slot.instance_id, error = %e, "strategy shutdown failed");
+            }
+        }
+        if self.shutdown_config.cancel_orders {
+            self.kill_switch
+                .cancel_open_orders(&self.orders, !self.shutdown_config.flatten)
+                .await;
+        }
+        if self.shutdown_config.flatten {
+            self.kill_switch
+                .flatten(&self.positions, "shutdown_flatten")
+                .await;
+        }
+        self.persist_state();
+        if let Some(journal) = &self.journal {
+            let record = serde_json::json!({
+                "event": "shutdown",
+                "instances": self.slots.iter().map(|s| &s.instance_id).collect::<Vec<_>>(),
+            });
+            if let Err(e) = journal.append(&record).and_then(|_| journal.sync()) {
+                warn!(error = %e, "failed to flush journal");
+            }
+        }
+        self.alerts.drain(ALERT_DRAIN_TIMEOUT).await;
+    }
+
+    fn persist_state(&mut self) {
+        if let Err(e) = self.kill_switch.flush() {
+            warn!(error = %e, "failed to persist kill switch state");
+        }
+        self.save_snapshots();
+    }
+
+    /// Unreadable snapshots are skipped so the instance starts fresh instead
+    /// of refusing to start.
+    fn restore(&mut self) {
+        let Some(store) = &self.snapshots else {
+            return;
+        };
+        let engine = store.load_with(ENGINE_SNAPSHOT_KEY, |snapshot| {
+            let state: EngineSnapshot = serde_json::from_value(snapshot.payload)?;
+            Ok((snapshot.saved_at, state))
+        });
+        match engine {
+            Ok(Some((saved_at, snapshot))) => {
+                info!(
+                    %saved_at,
+                    positions = snapshot.positions.len(),
+                    orders = snapshot.orders.len(),
+                    "restoring engine state"
+                );
+                self.positions.restore(snapshot.positions);
+                self.orders.restore(snapshot.orders);
+                self.seen_tids = snapshot.seen_tids;
+            }
+            Ok(None) => {}
+            Err(e) => warn!(error = %e, "failed to load engine snapshot"),
+        }
+        for slot in &mut self.slots {
+            let current = slot.strategy.state_version();
+            let strategy = &slot.strategy;
+            let mut decode = |snapshot: Snapshot<Value>| {
+                if snapshot.version > current {
+                    return Err(AppError::Strategy(format!(
+                        "snapshot version {} is newer than supported {current}",
+                        snapshot.version
+                    )));
+                }
+                if snapshot.version < current {
+                    return strategy.migrate_state(snapshot.version, snapshot.payload);
+                }
+                Ok(snapshot.payload)
+            };
+            let mut loaded = store.load_with(&snapshot_key(&slot.instance_id), &mut decode);
+            if let Ok(None) = loaded
+                && let Some(legacy) = strategy.legacy_snapshot_key(&slot.instance_id)
+            {
+                loaded = store.load_with(&legacy, &mut decode);
+                if let Ok(Some(_)) = loaded {
+                    info!(instance = %slot.instance_id, key = %legacy, "found strategy state under legacy key");
+                }
+            }
+            match loaded {
+                Ok(Some(state)) => {
+                    info!(instance = %slot.instance_id, "restoring strategy state");
+                    slot.strategy.restore_state(state);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(instance = %slot.instance_id, error = %e, "failed to load strategy snapshot")
+                }
+            }
+        }
+    }
+
+    fn save_snapshots(&self) {
+        let Some(store) = &self.snapshots else {
+            return;
+        };
+        let snapshot = EngineSnapshot {
+            positions: self.positions.snapshot(),
+            orders: self.orders.snapshot(),
+            seen_tids: self.seen_tids.clone(),
+        };
+        if let Err(e) = store.save(ENGINE_SNAPSHOT_KEY, &snapshot) {
+            warn!(error = %e, "engine snapshot failed");
+        }
+        for slot in &self.slots {
+            let state = slot.strategy.snapshot_state();
+            let version = slot.strategy.state_version();
+            if let Err(e) = store.save_versioned(&snapshot_key(&slot.instance_id), version, &state)
+            {
+                warn!(instance = %slot.instance_id, error = %e, "strategy snapshot failed");
+            }
+        }
+    }
+
     async fn handle_market_event(
         &mut self,
-        event: Result<crate::marketdata::events::MarketEvent, broadcast::error::RecvError>,
+        event: Option<Result<MarketEvent, BroadcastStreamRecvError>>,
     ) -> AppResult<bool> {
         match event {
-            Ok(event) => {
-                let resp = self.strategy.on_event(&mut self.ctx, event).await?;
-                for intent in resp.intents {
-                    self.ctx.submit_intent(intent).await?;
+            Some(Ok(event)) => {
+                self.risk.observe(&event);
+                if let MarketEvent::Book(book) = &event {
+                    self.books.apply(book);
+                }
+                if let Some(price) = event.price() {
+                    self.positions.update_mark(event.asset(), price);
                 }
+                for idx in 0..self.slots.len() {
+                    if !self.slots[idx].wants(&event) {
+                        continue;
+                    }
+                    let slot = &mut self.slots[idx];
+                    match slot.strategy.on_event(&mut slot.ctx, event.clone()).await {
+                        Ok(resp) => self.submit(idx, resp).await,
+                        Err(e) => self.report_slot(idx, "on_event", &e),
+                    }
+                }
+                self.check_kill_switch().await?;
                 Ok(true)
             }
-            Err(broadcast::error::RecvError::Closed) => Ok(false),
-            Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                tracing::warn!(skipped, "market event lagged");
+            Some(Err(BroadcastStreamRecvError::Lagged(skipped))) => {
+                warn!(skipped, "market events lagged");
                 Ok(true)
             }
+            None => Ok(false),
+        }
+    }
+
+    async fn handle_intervals(&mut self, scheduler: &mut Scheduler) -> AppResult<()> {
+        let now = self.clock.now();
+        for idx in scheduler.due(now) {
+            let slot = &mut self.slots[idx];
+            match slot.strategy.on_interval(&mut slot.ctx, now).await {
+                Ok(resp) => self.submit(idx, resp).await,
+                Err(e) => self.report_slot(idx, "on_interval", &e),
+            }
         }
+        self.check_kill_switch().await
     }
 
     async fn handle_fill(&mut self, fill: Option<FillEvent>) -> AppResult<bool> {
         match fill {
+            Some(fill) if !first_sighting(&mut self.seen_tids, &fill) => {
+                debug!(tid = ?fill.tid, "dropping fill already applied");
+                Ok(true)
+            }
             Some(fill) => {
                 self.positions.apply_fill(&fill);
-                let resp = self.strategy.on_fill(&mut self.ctx, fill.clone()).await?;
-                for intent in resp.intents {
-                    self.ctx.submit_intent(intent).await?;
+                let order = self.orders.apply_fill(&fill);
+                if let Err(e) = self.kill_switch.on_fill(&fill) {
+                    self.report("kill_switch", "persist", &e);
+                }
+                let owner = order.as_ref().and_then(|order| {
+                    self.slots
+                        .iter()
+                        .position(|s| s.instance_id == order.instance_id)
+                });
+                if let (Some(idx), Some(order)) = (owner, &order)
+                    && let Err(e) = self.slots[idx].ctx.on_order_fill(order).await
+                {
+                    self.report_slot(idx, "bracket", &e);
+                    close_unprotected(&self.kill_switch, &self.positions, &self.orders, order)
+                        .await;
+                }
+                let targets: Vec<usize> = match owner {
+                    Some(idx) => vec![idx],
+                    None => {
+                        debug!(cloid = ?fill.cloid, "fill has no owning instance, broadcasting");
+                        (0..self.slots.len()).collect()
+                    }
+                };
+                for idx in targets {
+                    let slot = &mut self.slots[idx];
+                    match slot.strategy.on_fill(&mut slot.ctx, fill.clone()).await {
+                        Ok(resp) => self.submit(idx, resp).await,
+                        Err(e) => self.report_slot(idx, "on_fill", &e),
+                    }
                 }
+                self.check_kill_switch().await?;
                 Ok(true)
             }
             None => Ok(false),
         }
     }
+
+    async fn check_kill_switch(&mut self) -> AppResult<()> {
+        if self.kill_switch.evaluate(&self.positions)? {
+            self.kill_switch
+                .enforce(&self.positions, &self.orders)
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn submit(&mut self, idx: usize, mut resp: StrategyResponse) {
+        if !resp.actions.is_empty() {
+            let actions = std::mem::take(&mut resp.actions);
+            let now = self.clock.now();
+            self.alerts
+                .notify(&self.slots[idx].instance_id, actions, now);
+        }
+        let dispatched = dispatch(
+            &self.slots[idx].ctx,
+            &self.risk,
+            &self.positions,
+            resp,
+            self.kill_switch.is_tripped(),
+        )
+        .await;
+        if let Err(e) = dispatched {
+            self.report_slot(idx, "dispatch", &e);
+        }
+    }
+
+    fn report_slot(&mut self, idx: usize, stage: &str, e: &AppError) {
+        let instance_id = self.slots[idx].instance_id.clone();
+        self.report(&instance_id, stage, e);
+    }
+
+    /// Errors from one instance, or from a background task like reconcile,
+    /// are logged and alerted on, at most once a minute per stage; the rest of
+    /// the engine keeps running.
+    fn report(&mut self, instance_id: &str, stage: &str, e: &AppError) {
+        error!(instance = instance_id, stage, error = %e, "instance step failed");
+        let alert = StrategyAction::alert(AlertSeverity::Critical, format!("{stage} failed: {e}"));
+        let now = self.clock.now();
+        self.alerts.notify_once(instance_id, stage, alert, now);
+    }
+}
+
+/// Records the fill's trade id and reports whether it is new. A fill replayed
+/// after a reconnect or restart must not reach positions, orders or the kill
+/// switch twice; fills without a trade id always count.
+fn first_sighting(seen: &mut VecDeque<u64>, fill: &FillEvent) -> bool {
+    let Some(tid) = fill.tid else {
+        return true;
+    };
+    if seen.contains(&tid) {
+        return false;
+    }
+    if seen.len() >= SEEN_FILLS {
+        seen.pop_front();
+    }
+    seen.push_back(tid);
+    true
+}
+
+/// Closes the filled part of a bracketed entry whose stop loss is not
+/// resting, rather than leave it open without one.
+pub(crate) async fn close_unprotected(
+    kill_switch: &KillSwitch,
+    positions: &PositionManager,
+    orders: &OrderManager,
+    entry: &TrackedOrder,
+) {
+    let wants_stop = entry
+        .bracket
+        .as_ref()
+        .is_some_and(|bracket| bracket.stop_loss.is_some());
+    let has_stop = orders
+        .open_siblings(&entry.cloid, &entry.cloid)
+        .iter()
+        .any(|leg| {
+            leg.trigger
+                .as_ref()
+                .is_some_and(|t| t.kind == TriggerKind::Sl)
+        });
+    if !wants_stop || has_stop || entry.filled.is_zero() {
+        return;
+    }
+    let size = match entry.side {
+        OrderSide::Buy => entry.filled,
+        OrderSide::Sell => -entry.filled,
+    };
+    warn!(entry = %entry.cloid, %size, "stop loss not resting, closing the fill");
+    kill_switch
+        .close(positions, &entry.asset, size, "unprotected_fill")
+        .await;
+}
+
+/// Applies a reconcile result to the kill switch. `Reconciler::reconcile`
+/// swallows fetch failures, so an error here is always halt-policy drift: it
+/// trips the switch and is handed back for reporting. Sizes overwritten by a
+/// resync are booked with the switch so its PnL does not jump.
+pub(crate) async fn settle_reconcile(
+    result: AppResult<Vec<PositionDrift>>,
+    kill_switch: &mut KillSwitch,
+    positions: &PositionManager,
+    orders: &OrderManager,
+) -> AppResult<()> {
+    match result {
+        Ok(resynced) => {
+            for drift in resynced {
+                let price = positions.mark(&drift.asset).unwrap_or(drift.exchange_entry);
+                kill_switch.on_resync(drift.exchange_size - drift.local_size, price)?;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            kill_switch.halt(&format!("reconciliation halted: {e}"))?;
+            kill_switch.enforce(positions, orders).await;
+            Err(e)
+        }
+    }
+}
+
+/// Executes a strategy response: one cancel batch, then risk-screened
+/// modifies, then the risk-screened intents as one order batch. Cancels
+/// only reduce exposure, so they still run once `halted`; everything else
+/// is dropped.
+pub(crate) async fn dispatch(
+    ctx: &StrategyContext,
+    risk: &RiskLimits,
+    positions: &PositionManager,
+    resp: StrategyResponse,
+    halted: bool,
+) -> AppResult<()> {
+    if !resp.cancels.is_empty()
+        && let Err(e) = ctx.cancel_batch(resp.cancels).await
+    {
+        warn!(instance = ctx.instance_id(), error = %e, "cancel batch failed");
+    }
+
+    if halted {
+        let dropped = resp.intents.len() + resp.modifies.len();
+        if dropped > 0 {
+            warn!(
+                instance = ctx.instance_id(),
+                dropped, "kill switch tripped, dropping intents"
+            );
+        }
+        return Ok(());
+    }
+
+    for modify in resp.modifies {
+        let (oid, intent) = match ctx.modified_intent(&modify) {
+            Ok(replacement) => replacement,
+            Err(e) => {
+                warn!(instance = ctx.instance_id(), order = ?modify.order, error = %e, "modify skipped");
+                continue;
+            }
+        };
+        if risk.screen_modify(ctx, positions, &intent)
+            && let Err(e) = ctx.modify(oid, intent).await
+        {
+            warn!(instance = ctx.instance_id(), oid, error = %e, "modify failed");
+        }
+    }
+
+    let intents = risk.screen(ctx, positions, resp.intents)?;
+    ctx.submit_batch(intents).await?;
+    Ok(())
+}
+
+/// Store key for the engine-managed copy of an instance's `snapshot_state`.
+fn snapshot_key(instance_id: &str) -> String {
+    format!("strategy_{instance_id}")
+}
+
+fn timer(period: Option<Duration>) -> Option<Interval> {
+    period.map(|period| {
+        let mut timer = interval_at(Instant::now() + period, period);
+        timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        timer
+    })
+}
+
+async fn next_tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => future::pending().await,
+    }
+}
+
+async fn sleep_for(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => future::pending().await,
+    }
+}
+
+/// `false` when the sender went away without signalling.
+async fn next_signal(signal: &mut Option<oneshot::Receiver<()>>) -> bool {
+    match signal {
+        Some(signal) => signal.await.is_ok(),
+        None => future::pending().await,
+    }
+}
+
+async fn next_message<T>(rx: &mut Option<UnboundedReceiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{KillSwitchConfig, RiskConfig};
+    use crate::exchange::ExecutionVenue;
+    use crate::exchange::order_router::{OrderIntent, OrderTif, TpSlBracket};
+    use crate::exchange::sim_venue::SimulatedVenue;
+    use rust_decimal::Decimal;
+    use uuid::Uuid;
+
+    struct Harness {
+        venue: Arc<SimulatedVenue>,
+        fills: UnboundedReceiver<FillEvent>,
+        positions: Arc<PositionManager>,
+        orders: Arc<OrderManager>,
+        kill_switch: KillSwitch,
+        ctx: StrategyContext,
+    }
+
+    fn harness() -> Harness {
+        let dir = std::env::temp_dir().join(format!("snivy-runner-{}", Uuid::new_v4()));
+        let journal = Arc::new(Journal::new(dir.join("journal.log")).unwrap());
+        let (venue, fills) = SimulatedVenue::new();
+        let venue = Arc::new(venue);
+        venue.update_mark("BTC", Decimal::from(100));
+        let positions = Arc::new(PositionManager::new());
+        let orders = Arc::new(OrderManager::new());
+        let kill_switch = KillSwitch::new(
+            KillSwitchConfig::default(),
+            SnapshotStore::new(&dir),
+            venue.clone(),
+            journal.clone(),
+        )
+        .unwrap();
+        let ctx = StrategyContext::new(
+            "test",
+            venue.clone(),
+            positions.clone(),
+            orders.clone(),
+            Arc::new(OrderBooks::new()),
+            journal,
+        );
+        Harness {
+            venue,
+            fills,
+            positions,
+            orders,
+            kill_switch,
+            ctx,
+        }
+    }
+
+    fn resting_bid() -> StrategyResponse {
+        let intent = OrderIntent::new(
+            "BTC",
+            OrderSide::Buy,
+            Decimal::ONE,
+            Decimal::from(90),
+            OrderTif::Gtc,
+        )
+        .unwrap();
+        StrategyResponse::with_intent(intent)
+    }
+
+    #[tokio::test]
+    async fn halt_drift_stops_dispatch() {
+        let mut h = harness();
+        let risk = RiskLimits::new(RiskConfig::default());
+        let drift = Err(AppError::Exchange("drift on 1 position(s)".into()));
+        let settled = settle_reconcile(drift, &mut h.kill_switch, &h.positions, &h.orders).await;
+        assert!(settled.is_err());
+        assert!(h.kill_switch.is_tripped());
+
+        let halted = h.kill_switch.is_tripped();
+        dispatch(&h.ctx, &risk, &h.positions, resting_bid(), halted)
+            .await
+            .unwrap();
+        assert!(h.venue.open_orders().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resync_does_not_move_kill_switch_pnl() {
+        let mut h = harness();
+        h.positions.update_mark("BTC", Decimal::from(100));
+        h.positions
+            .sync_position("BTC", Decimal::ONE, Decimal::from(100));
+        let before = h.kill_switch.pnl(&h.positions);
+
+        h.positions
+            .sync_position("BTC", Decimal::from(3), Decimal::from(95));
+        let resynced = vec![PositionDrift {
+            asset: "BTC".into(),
+            local_size: Decimal::ONE,
+            exchange_size: Decimal::from(3),
+            exchange_entry: Decimal::from(95),
+        }];
+        settle_reconcile(Ok(resynced), &mut h.kill_switch, &h.positions, &h.orders)
+            .await
+            .unwrap();
+        assert_eq!(h.kill_switch.pnl(&h.positions), before);
+        assert!(!h.kill_switch.is_tripped());
+    }
+
+    #[tokio::test]
+    async fn fill_without_a_resting_stop_is_closed() {
+        let mut h = harness();
+        h.positions.update_mark("BTC", Decimal::from(100));
+        let entry = OrderIntent::new(
+            "BTC",
+            OrderSide::Buy,
+            Decimal::ONE,
+            Decimal::from(101),
+            OrderTif::Ioc,
+        )
+        .unwrap()
+        .with_bracket(TpSlBracket {
+            take_profit: None,
+            stop_loss: Some(Decimal::from(90)),
+        });
+        h.venue.submit(entry.clone()).await.unwrap();
+        h.fills.try_recv().unwrap();
+
+        h.orders.track("test", &entry, "0xentry", None);
+        let mut order = h.orders.get("0xentry").unwrap();
+        order.filled = Decimal::ONE;
+        close_unprotected(&h.kill_switch, &h.positions, &h.orders, &order).await;
+
+        let close = h.fills.try_recv().unwrap();
+        assert!(!close.is_buy);
+        assert_eq!(close.size, Decimal::ONE);
+    }
+
+    fn fill(tid: Option<u64>) -> FillEvent {
+        FillEvent {
+            asset: "BTC".into(),
+            price: Decimal::from(100),
+            size: Decimal::ONE,
+            is_buy: true,
+            cloid: None,
+            fee: Decimal::ZERO,
+            tid,
+        }
+    }
+
+    #[test]
+    fn replayed_fill_is_seen_once() {
+        let mut seen = VecDeque::new();
+        assert!(first_sighting(&mut seen, &fill(Some(7))));
+        assert!(!first_sighting(&mut seen, &fill(Some(7))));
+        assert!(first_sighting(&mut seen, &fill(Some(8))));
+    }
+
+    #[test]
+    fn fills_without_tid_always_count() {
+        let mut seen = VecDeque::new();
+        assert!(first_sighting(&mut seen, &fill(None)));
+        assert!(first_sighting(&mut seen, &fill(None)));
+    }
 }
@@ -0,0 +1,122 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+/// Tracks when each strategy's `on_interval` cadences are next due. Ticks
+/// land on multiples of the cadence since the epoch, so a replay hits the
+/// same timestamps whatever instant it starts from.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    timers: Vec<Timer>,
+}
+
+#[derive(Debug)]
+struct Timer {
+    slot: usize,
+    every_ms: i64,
+    next_ms: i64,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cadences below one millisecond are ignored.
+    pub fn add(&mut self, slot: usize, every: Duration, now: DateTime<Utc>) {
+        let every_ms = every.as_millis() as i64;
+        if every_ms <= 0 {
+            return;
+        }
+        self.timers.push(Timer {
+            slot,
+            every_ms,
+            next_ms: next_boundary(now.timestamp_millis(), every_ms),
+        });
+    }
+
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.timers
+            .iter()
+            .map(|t| t.next_ms)
+            .min()
+            .and_then(DateTime::from_timestamp_millis)
+    }
+
+    /// Slots with a cadence due at `now`, each listed once even when several
+    /// of its cadences coincide. Missed ticks are skipped, not replayed.
+    pub fn due(&mut self, now: DateTime<Utc>) -> Vec<usize> {
+        let now_ms = now.timestamp_millis();
+        let mut slots = Vec::new();
+        for timer in &mut self.timers {
+            if timer.next_ms > now_ms {
+                continue;
+            }
+            timer.next_ms = next_boundary(now_ms, timer.every_ms);
+            if !slots.contains(&timer.slot) {
+                slots.push(timer.slot);
+            }
+        }
+        slots
+    }
+}
+
+fn next_boundary(now_ms: i64, every_ms: i64) -> i64 {
+    (now_ms.div_euclid(every_ms) + 1) * every_ms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::time::{Clock, ManualClock};
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+
+    #[test]
+    fn ticks_land_on_cadence_boundaries() {
+        let clock = ManualClock::new(at(30));
+        let mut scheduler = Scheduler::new();
+        scheduler.add(0, Duration::from_secs(60), clock.now());
+        assert_eq!(scheduler.next_due(), Some(at(60)));
+
+        clock.set(at(59));
+        assert!(scheduler.due(clock.now()).is_empty());
+        clock.set(at(60));
+        assert_eq!(scheduler.due(clock.now()), vec![0]);
+        assert_eq!(scheduler.next_due(), Some(at(120)));
+    }
+
+    #[test]
+    fn missed_ticks_are_skipped() {
+        let clock = ManualClock::new(at(0));
+        let mut scheduler = Scheduler::new();
+        scheduler.add(0, Duration::from_secs(60), clock.now());
+
+        clock.set(at(330));
+        assert_eq!(scheduler.due(clock.now()), vec![0]);
+        assert_eq!(scheduler.next_due(), Some(at(360)));
+    }
+
+    #[test]
+    fn coinciding_cadences_report_a_slot_once() {
+        let clock = ManualClock::new(at(0));
+        let mut scheduler = Scheduler::new();
+        scheduler.add(1, Duration::from_secs(30), clock.now());
+        scheduler.add(1, Duration::from_secs(60), clock.now());
+        scheduler.add(2, Duration::from_secs(60), clock.now());
+
+        clock.set(at(30));
+        assert_eq!(scheduler.due(clock.now()), vec![1]);
+        clock.set(at(60));
+        assert_eq!(scheduler.due(clock.now()), vec![1, 2]);
+    }
+
+    #[test]
+    fn sub_millisecond_cadences_are_ignored() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add(0, Duration::from_micros(500), at(0));
+        assert_eq!(scheduler.next_due(), None);
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use parking_lot::RwLock;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::errors::{AppError, AppResult};
+use crate::exchange::InfoService;
+use crate::exchange::order_router::OrderIntent;
+
+/// Perp prices may carry at most this many decimals minus `sz_decimals`.
+const MAX_PERP_DECIMALS: u32 = 6;
+const PRICE_SIG_FIGS: i32 = 5;
+pub const MIN_ORDER_NOTIONAL: Decimal = Decimal::TEN;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetInfo {
+    pub name: String,
+    pub index: u32,
+    pub sz_decimals: u32,
+    pub max_leverage: usize,
+}
+
+impl AssetInfo {
+    /// Truncates toward zero so rounding never grows an order past a limit.
+    pub fn round_size(&self, size: Decimal) -> Decimal {
+        size.round_dp_with_strategy(self.sz_decimals, RoundingStrategy::ToZero)
+            .normalize()
+    }
+
+    /// Five significant figures and at most `6 - sz_decimals` decimals;
+    /// integer prices are always valid whatever their magnitude.
+    pub fn round_price(&self, price: Decimal) -> Decimal {
+        if price <= Decimal::ZERO {
+            return price;
+        }
+        let sig_decimals = (PRICE_SIG_FIGS - 1 - magnitude(price)).max(0) as u32;
+        let max_decimals = MAX_PERP_DECIMALS.saturating_sub(self.sz_decimals);
+        price
+            .round_dp_with_strategy(
+                sig_decimals.min(max_decimals),
+                RoundingStrategy::MidpointAwayFromZero,
+            )
+            .normalize()
+    }
+}
+
+/// Power of ten of the leading digit, e.g. 2 for 123.4 and -3 for 0.00123.
+fn magnitude(price: Decimal) -> i32 {
+    let mut scaled = price;
+    let mut magnitude = 0;
+    while scaled >= Decimal::TEN {
+        scaled /= Decimal::TEN;
+        magnitude += 1;
+    }
+    while scaled < Decimal::ONE {
+        scaled *= Decimal::TEN;
+        magnitude -= 1;
+    }
+    magnitude
+}
+
+/// Exchange `meta` loaded once at startup; `refresh` picks up new listings.
+#[derive(Default)]
+pub struct AssetMetaCache {
+    assets: RwLock<HashMap<String, AssetInfo>>,
+}
+
+impl AssetMetaCache {
+    pub async fn load(info: &InfoService) -> AppResult<Self> {
+        let cache = Self::default();
+        cache.refresh(info).await?;
+        Ok(cache)
+    }
+
+    pub async fn refresh(&self, info: &InfoService) -> AppResult<()> {
+        let assets = info.asset_meta().await?;
+        info!(assets = assets.len(), "asset metadata loaded");
+        *self.assets.write() = assets.into_iter().map(|a| (a.name.clone(), a)).collect();
+        Ok(())
+    }
+
+    pub fn get(&self, asset: &str) -> Option<AssetInfo> {
+        self.assets.read().get(asset).cloned()
+    }
+
+    /// Rounds size, limit and trigger prices in place and rejects orders the
+    /// exchange would refuse. Reduce-only orders may be below the minimum
+    /// notional so dust positions can still be closed.
+    pub fn normalize(&self, intent: &mut OrderIntent) -> AppResult<()> {
+        let asset = self
+            .get(&intent.asset)
+            .ok_or_else(|| AppError::Config(format!("unknown asset {}", intent.asset)))?;
+
+        let size = asset.round_size(intent.size);
+        if size <= Decimal::ZERO {
+            return Err(AppError::Exchange(format!(
+                "size {} rounds to zero at {} decimals",
+                intent.size, asset.sz_decimals
+            )));
+        }
+        let limit_px = asset.round_price(intent.limit_px);
+        let notional = size * limit_px;
+        if !intent.reduce_only && notional < MIN_ORDER_NOTIONAL {
+            return Err(AppError::Exchange(format!(
+                "order value {notional:.2} below minimum {MIN_ORDER_NOTIONAL}"
+            )));
+        }
+
+        intent.size = size;
+        intent.limit_px = limit_px;
+        if let Some(trigger) = &mut intent.trigger {
+            trigger.trigger_px = asset.round_price(trigger.trigger_px);
+        }
+        if let Some(bracket) = &mut intent.bracket {
+            for px in [&mut bracket.take_profit, &mut bracket.stop_loss]
+                .into_iter()
+                .flatten()
+            {
+                *px = asset.round_price(*px);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::order_router::{OrderSide, OrderTif};
+
+    fn asset(sz_decimals: u32) -> AssetInfo {
+        AssetInfo {
+            name: "BTC".into(),
+            index: 0,
+            sz_decimals,
+            max_leverage: 50,
+        }
+    }
+
+    fn cache() -> AssetMetaCache {
+        let cache = AssetMetaCache::default();
+        cache.assets.write().insert("BTC".into(), asset(5));
+        cache
+    }
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn prices_keep_five_significant_figures() {
+        assert_eq!(asset(5).round_price(dec("12345.67")), dec("12346"));
+        assert_eq!(asset(0).round_price(dec("1.234567")), dec("1.2346"));
+        assert_eq!(asset(0).round_price(dec("0.0123456")), dec("0.012346"));
+    }
+
+    #[test]
+    fn prices_respect_the_decimal_cap() {
+        assert_eq!(asset(4).round_price(dec("0.0123456")), dec("0.01"));
+    }
+
+    #[test]
+    fn integer_prices_are_left_alone() {
+        assert_eq!(asset(5).round_price(dec("123456")), dec("123456"));
+    }
+
+    #[test]
+    fn sizes_truncate_toward_zero() {
+        assert_eq!(asset(5).round_size(dec("0.123456789")), dec("0.12345"));
+        assert_eq!(asset(2).round_size(dec("1.999")), dec("1.99"));
+    }
+
+    #[test]
+    fn normalize_rejects_orders_below_min_notional() {
+        let mut intent = OrderIntent::new(
+            "BTC",
+            OrderSide::Buy,
+            dec("0.0001"),
+            dec("50000"),
+            OrderTif::Gtc,
+        )
+        .unwrap();
+        assert!(cache().normalize(&mut intent).is_err());
+    }
+
+    #[test]
+    fn normalize_lets_reduce_only_dust_through() {
+        let mut intent = OrderIntent::new(
+            "BTC",
+            OrderSide::Sell,
+            dec("0.0001"),
+            dec("50000"),
+            OrderTif::Gtc,
+        )
+        .unwrap()
+        .with_reduce_only(true);
+        assert!(cache().normalize(&mut intent).is_ok());
+    }
+
+    #[test]
+    fn normalize_rejects_sizes_that_round_to_zero() {
+        let mut intent = OrderIntent::new(
+            "BTC",
+            OrderSide::Buy,
+            dec("0.000001"),
+            dec("50000"),
+            OrderTif::Gtc,
+        )
+        .unwrap()
+        .with_reduce_only(true);
+        assert!(cache().normalize(&mut intent).is_err());
+    }
+
+    #[test]
+    fn normalize_rounds_size_and_price() {
+        let mut intent = OrderIntent::new(
+            "BTC",
+            OrderSide::Buy,
+            dec("0.0123456"),
+            dec("65432.19"),
+            OrderTif::Gtc,
+        )
+        .unwrap();
+        cache().normalize(&mut intent).unwrap();
+        assert_eq!(intent.size, dec("0.01234"));
+        assert_eq!(intent.limit_px, dec("65432"));
+    }
+}
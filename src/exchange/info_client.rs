@@ -1,33 +1,48 @@
 use std::sync::Arc;
 
 use alloy::primitives::Address;
-use chrono::Utc;
+use chrono::{TimeZone, Utc};
 use hyperliquid_rust_sdk::{BaseUrl, InfoClient, Message, Subscription};
+use rust_decimal::Decimal;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::{self, UnboundedReceiver};
 use tracing::instrument;
 
 use crate::errors::{AppError, AppResult};
+use crate::exchange::asset_meta::AssetInfo;
+use crate::exchange::order_router::OrderSide;
+use crate::exchange::position_manager::Position;
+use crate::exchange::rate_limit::{INFO_LIGHT_WEIGHT, INFO_WEIGHT, RequestBudget};
+use crate::exchange::venue::OpenOrder;
+use crate::marketdata::events::CandleEvent;
 use crate::utils::time::interval_to_millis;
 use hyperliquid_rust_sdk::CandlesSnapshotResponse;
 
 #[derive(Clone)]
 pub struct InfoService {
     inner: Arc<Mutex<InfoClient>>,
+    budget: Arc<RequestBudget>,
 }
 
 impl InfoService {
-    pub async fn connect(base_url: BaseUrl) -> AppResult<Self> {
+    pub async fn connect(base_url: BaseUrl, budget: Arc<RequestBudget>) -> AppResult<Self> {
         let client = InfoClient::with_reconnect(None, Some(base_url))
             .await
             .map_err(|e| AppError::Exchange(e.to_string()))?;
         Ok(Self {
             inner: Arc::new(Mutex::new(client)),
+            budget,
         })
     }
 
+    /// The REST budget shared with the order router.
+    pub fn budget(&self) -> Arc<RequestBudget> {
+        self.budget.clone()
+    }
+
     #[instrument(skip(self))]
-    pub async fn latest_price(&self, asset: &str) -> AppResult<f64> {
+    pub async fn latest_price(&self, asset: &str) -> AppResult<Decimal> {
+        self.budget.acquire(INFO_LIGHT_WEIGHT).await;
         let guard = self.inner.lock().await;
         let mids = guard
             .all_mids()
@@ -36,7 +51,7 @@ impl InfoService {
         let price = mids
             .get(asset)
             .ok_or_else(|| AppError::Exchange(format!("asset {asset} not found in mids")))?
-            .parse::<f64>()
+            .parse::<Decimal>()
             .map_err(|e| AppError::Exchange(e.to_string()))?;
         Ok(price)
     }
@@ -47,7 +62,7 @@ impl InfoService {
         asset: &str,
         interval: &str,
         count: usize,
-    ) -> AppResult<Vec<f64>> {
+    ) -> AppResult<Vec<CandleEvent>> {
         let window_ms = interval_to_millis(interval).ok_or_else(|| {
             AppError::Config(format!(
                 "unsupported interval '{interval}' for MA bootstrap"
@@ -55,6 +70,7 @@ impl InfoService {
         })?;
         let end_time = Utc::now().timestamp_millis() as u64;
         let start_time = end_time.saturating_sub(window_ms * count as u64);
+        self.budget.acquire(INFO_WEIGHT).await;
         let guard = self.inner.lock().await;
         let candles = guard
             .candles_snapshot(
@@ -65,14 +81,108 @@ impl InfoService {
             )
             .await
             .map_err(|e| AppError::Exchange(e.to_string()))?;
-        Ok(Self::extract_closes(candles))
+        Ok(candles
+            .into_iter()
+            .filter_map(|c| Self::map_candle(asset, c, end_time))
+            .collect())
+    }
+
+    /// The last candle of a snapshot is usually still forming.
+    fn map_candle(
+        asset: &str,
+        candle: CandlesSnapshotResponse,
+        now_ms: u64,
+    ) -> Option<CandleEvent> {
+        Some(CandleEvent {
+            asset: asset.to_string(),
+            open: candle.open.parse::<Decimal>().ok()?,
+            high: candle.high.parse::<Decimal>().ok()?,
+            low: candle.low.parse::<Decimal>().ok()?,
+            close: candle.close.parse::<Decimal>().ok()?,
+            volume: candle.vlm.parse::<Decimal>().ok()?,
+            trades: candle.num_trades,
+            open_time: Utc.timestamp_millis_opt(candle.time_open as i64).single()?,
+            timestamp: Utc
+                .timestamp_millis_opt(candle.time_close as i64)
+                .single()?,
+            interval: candle.candle_interval,
+            closed: candle.time_close < now_ms,
+        })
     }
 
-    fn extract_closes(candles: Vec<CandlesSnapshotResponse>) -> Vec<f64> {
-        candles
+    #[instrument(skip(self))]
+    pub async fn asset_meta(&self) -> AppResult<Vec<AssetInfo>> {
+        self.budget.acquire(INFO_WEIGHT).await;
+        let guard = self.inner.lock().await;
+        let meta = guard
+            .meta()
+            .await
+            .map_err(|e| AppError::Exchange(e.to_string()))?;
+        Ok(meta
+            .universe
+            .into_iter()
+            .enumerate()
+            .map(|(index, asset)| AssetInfo {
+                name: asset.name,
+                index: index as u32,
+                sz_decimals: asset.sz_decimals,
+                max_leverage: asset.max_leverage,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn positions(&self, address: Address) -> AppResult<Vec<Position>> {
+        self.budget.acquire(INFO_LIGHT_WEIGHT).await;
+        let guard = self.inner.lock().await;
+        let state = guard
+            .user_state(address)
+            .await
+            .map_err(|e| AppError::Exchange(e.to_string()))?;
+        Ok(state
+            .asset_positions
+            .into_iter()
+            .filter_map(|p| {
+                let data = p.position;
+                Some(Position {
+                    size: data.szi.parse::<Decimal>().ok()?,
+                    entry_price: data
+                        .entry_px
+                        .and_then(|px| px.parse::<Decimal>().ok())
+                        .unwrap_or_default(),
+                    asset: data.coin,
+                    realized_pnl: Decimal::ZERO,
+                    fees: Decimal::ZERO,
+                })
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn open_orders(&self, address: Address) -> AppResult<Vec<OpenOrder>> {
+        self.budget.acquire(INFO_WEIGHT).await;
+        let guard = self.inner.lock().await;
+        let orders = guard
+            .open_orders(address)
+            .await
+            .map_err(|e| AppError::Exchange(e.to_string()))?;
+        Ok(orders
             .into_iter()
-            .filter_map(|c| c.close.parse::<f64>().ok())
-            .collect()
+            .filter_map(|o| {
+                Some(OpenOrder {
+                    side: if o.side.eq_ignore_ascii_case("B") {
+                        OrderSide::Buy
+                    } else {
+                        OrderSide::Sell
+                    },
+                    size: o.sz.parse::<Decimal>().ok()?,
+                    limit_px: o.limit_px.parse::<Decimal>().ok()?,
+                    asset: o.coin,
+                    oid: o.oid,
+                    cloid: None,
+                })
+            })
+            .collect())
     }
 
     pub async fn subscribe(
@@ -1,9 +1,21 @@
+pub mod asset_meta;
 pub mod info_client;
+pub mod order_manager;
 pub mod order_router;
 pub mod position_manager;
+pub mod rate_limit;
+pub mod reconciler;
+pub mod sim_venue;
+pub mod venue;
 pub mod ws_client;
 
+pub use asset_meta::{AssetInfo, AssetMetaCache};
 pub use info_client::InfoService;
+pub use order_manager::{OrderManager, OrderState, OrderUpdateEvent, TrackedOrder};
 pub use order_router::{OrderIntent, OrderRouter};
 pub use position_manager::{FillEvent, PositionManager};
-pub use ws_client::{MarketStream, user_fills_stream};
+pub use rate_limit::RequestBudget;
+pub use reconciler::Reconciler;
+pub use sim_venue::SimulatedVenue;
+pub use venue::{AckStatus, ExecutionVenue, OpenOrder, OrderAck, OrderRef};
+pub use ws_client::{MarketStream, order_updates_stream, user_fills_stream};
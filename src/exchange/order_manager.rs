@@ -0,0 +1,313 @@
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument, warn};
+
+use crate::exchange::FillEvent;
+use crate::exchange::order_router::{
+    OrderIntent, OrderSide, OrderTif, OrderTrigger, TpSlBracket, format_cloid,
+};
+use crate::exchange::venue::{AckStatus, OrderAck, OrderRef};
+use crate::utils::time::now;
+
+const TERMINAL_RETENTION_MINS: i64 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderState {
+    Pending,
+    Resting,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderState {
+    pub fn is_open(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Pending | OrderState::Resting | OrderState::PartiallyFilled
+        )
+    }
+
+    /// Maps Hyperliquid `orderUpdates` status strings, e.g. `open`, `filled`,
+    /// `canceled`, `marginCanceled`, `rejected`, `triggered`.
+    pub fn from_exchange_status(status: &str) -> Option<Self> {
+        match status {
+            "open" | "triggered" => Some(OrderState::Resting),
+            "filled" => Some(OrderState::Filled),
+            s if s.eq_ignore_ascii_case("canceled") || s.ends_with("Canceled") => {
+                Some(OrderState::Cancelled)
+            }
+            s if s.eq_ignore_ascii_case("rejected") || s.ends_with("Rejected") => {
+                Some(OrderState::Rejected)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderUpdateEvent {
+    pub asset: String,
+    pub oid: u64,
+    pub cloid: Option<String>,
+    pub status: String,
+    pub size: Decimal,
+    pub orig_size: Decimal,
+    pub limit_px: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedOrder {
+    pub cloid: String,
+    pub oid: Option<u64>,
+    pub instance_id: String,
+    pub client_tag: String,
+    pub asset: String,
+    pub side: OrderSide,
+    pub size: Decimal,
+    pub filled: Decimal,
+    pub limit_px: Decimal,
+    pub tif: OrderTif,
+    pub reduce_only: bool,
+    pub trigger: Option<OrderTrigger>,
+    /// Not yet placed bracket for an entry; taken once the entry completes.
+    pub bracket: Option<TpSlBracket>,
+    /// Cloid of the entry this order protects, shared by both bracket legs.
+    pub group: Option<String>,
+    pub state: OrderState,
+    pub reason: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TrackedOrder {
+    pub fn remaining(&self) -> Decimal {
+        (self.size - self.filled).max(Decimal::ZERO)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.filled > Decimal::ZERO && self.filled >= self.size
+    }
+
+    fn transition(&mut self, state: OrderState) {
+        // terminal states are final; late resting acks must not reopen an order
+        if self.state.is_open() {
+            self.state = state;
+        }
+        self.updated_at = now();
+    }
+}
+
+/// Tracks every order submitted through a `StrategyContext`, keyed by cloid.
+#[derive(Default)]
+pub struct OrderManager {
+    orders: DashMap<String, TrackedOrder>,
+    oids: DashMap<u64, String>,
+}
+
+impl OrderManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(&self, instance_id: &str, intent: &OrderIntent, cloid: &str, group: Option<&str>) {
+        self.prune_terminal();
+        let order = TrackedOrder {
+            cloid: cloid.to_string(),
+            oid: None,
+            instance_id: instance_id.to_string(),
+            client_tag: intent.client_tag.clone(),
+            asset: intent.asset.clone(),
+            side: intent.side,
+            size: intent.size,
+            filled: Decimal::ZERO,
+            limit_px: intent.limit_px,
+            tif: intent.tif,
+            reduce_only: intent.reduce_only,
+            trigger: intent.trigger.clone(),
+            bracket: intent.bracket.clone(),
+            group: group.map(str::to_string),
+            state: OrderState::Pending,
+            reason: None,
+            updated_at: now(),
+        };
+        self.orders.insert(cloid.to_string(), order);
+    }
+
+    pub fn apply_ack(&self, ack: &OrderAck) {
+        let Some(mut order) = self.orders.get_mut(&ack.cloid) else {
+            return;
+        };
+        match &ack.status {
+            AckStatus::Resting { oid } => {
+                order.oid = Some(*oid);
+                self.oids.insert(*oid, ack.cloid.clone());
+                order.transition(OrderState::Resting);
+            }
+            AckStatus::Filled { oid, total_sz, .. } => {
+                order.oid = Some(*oid);
+                self.oids.insert(*oid, ack.cloid.clone());
+                // an IOC can execute less than requested; the ack is the
+                // authoritative executed size the fills will add up to
+                if *total_sz > Decimal::ZERO {
+                    order.size = *total_sz;
+                }
+                order.transition(OrderState::Filled);
+            }
+            AckStatus::Rejected { reason } => {
+                order.reason = Some(reason.clone());
+                order.transition(OrderState::Rejected);
+            }
+            AckStatus::Pending => {}
+        }
+    }
+
+    pub fn reject(&self, cloid: &str, reason: &str) {
+        if let Some(mut order) = self.orders.get_mut(cloid) {
+            order.reason = Some(reason.to_string());
+            order.transition(OrderState::Rejected);
+        }
+    }
+
+    /// Filled quantity is driven only by fills so acks and order updates that
+    /// also report fills are not double counted. Returns the updated order.
+    #[instrument(skip(self))]
+    pub fn apply_fill(&self, fill: &FillEvent) -> Option<TrackedOrder> {
+        let cloid = fill.cloid.as_ref()?.to_lowercase();
+        let Some(mut order) = self.orders.get_mut(&cloid) else {
+            debug!(cloid, "fill for untracked order");
+            return None;
+        };
+        order.filled += fill.size;
+        let state = if order.filled >= order.size {
+            OrderState::Filled
+        } else {
+            OrderState::PartiallyFilled
+        };
+        order.transition(state);
+        Some(order.clone())
+    }
+
+    pub fn take_bracket(&self, cloid: &str) -> Option<TpSlBracket> {
+        self.orders.get_mut(cloid)?.bracket.take()
+    }
+
+    /// Open orders in the bracket group other than `cloid`.
+    pub fn open_siblings(&self, group: &str, cloid: &str) -> Vec<TrackedOrder> {
+        self.orders
+            .iter()
+            .filter(|o| o.state.is_open() && o.cloid != cloid)
+            .filter(|o| o.group.as_deref() == Some(group))
+            .map(|o| o.value().clone())
+            .collect()
+    }
+
+    pub fn apply_update(&self, update: &OrderUpdateEvent) {
+        let cloid = update
+            .cloid
+            .as_ref()
+            .map(|c| c.to_lowercase())
+            .or_else(|| self.oids.get(&update.oid).map(|c| c.value().clone()));
+        let Some(cloid) = cloid else {
+            debug!(oid = update.oid, "order update for untracked order");
+            return;
+        };
+        let Some(mut order) = self.orders.get_mut(&cloid) else {
+            return;
+        };
+        let Some(mut state) = OrderState::from_exchange_status(&update.status) else {
+            debug!(status = %update.status, "unknown order status");
+            return;
+        };
+        if order.oid != Some(update.oid) {
+            // a modify re-places the order under a new oid and cancels the old
+            // one; only the new oid's open status moves the order forward
+            if order.oid.is_some() && state != OrderState::Resting {
+                return;
+            }
+            order.oid = Some(update.oid);
+            self.oids.insert(update.oid, cloid.clone());
+        }
+        if state == OrderState::Resting && update.size < update.orig_size {
+            state = OrderState::PartiallyFilled;
+        }
+        order.transition(state);
+    }
+
+    pub fn apply_modify(&self, cloid: &str, size: Decimal, limit_px: Decimal) {
+        if let Some(mut order) = self.orders.get_mut(cloid) {
+            order.size = size;
+            order.limit_px = limit_px;
+            order.updated_at = now();
+        }
+    }
+
+    pub fn mark_cancelled(&self, cloid: &str) {
+        if let Some(mut order) = self.orders.get_mut(cloid) {
+            order.transition(OrderState::Cancelled);
+        }
+    }
+
+    pub fn cloid_for_oid(&self, oid: u64) -> Option<String> {
+        self.oids.get(&oid).map(|c| c.value().clone())
+    }
+
+    pub fn resolve(&self, order: &OrderRef) -> Option<TrackedOrder> {
+        let cloid = match order {
+            OrderRef::Oid(oid) => self.cloid_for_oid(*oid)?,
+            OrderRef::Cloid(cloid) => format_cloid(cloid),
+        };
+        self.get(&cloid)
+    }
+
+    pub fn get(&self, cloid: &str) -> Option<TrackedOrder> {
+        self.orders.get(cloid).map(|o| o.value().clone())
+    }
+
+    pub fn snapshot(&self) -> Vec<TrackedOrder> {
+        self.orders.iter().map(|o| o.value().clone()).collect()
+    }
+
+    /// Re-tracks orders from a saved snapshot; the reconciler later retires
+    /// any that stopped resting while the process was down. Open orders that
+    /// never got an oid cannot be reconciled or cancelled, so they are dropped.
+    pub fn restore(&self, orders: Vec<TrackedOrder>) {
+        for order in orders {
+            match order.oid {
+                Some(oid) => {
+                    self.oids.insert(oid, order.cloid.clone());
+                }
+                None if order.state.is_open() => {
+                    warn!(cloid = %order.cloid, "dropping restored order without an oid");
+                    continue;
+                }
+                None => {}
+            }
+            self.orders.insert(order.cloid.clone(), order);
+        }
+    }
+
+    /// Open orders across all instances when `instance_id` is `None`.
+    pub fn open_orders(&self, instance_id: Option<&str>) -> Vec<TrackedOrder> {
+        self.orders
+            .iter()
+            .filter(|o| o.state.is_open())
+            .filter(|o| instance_id.is_none_or(|id| o.instance_id == id))
+            .map(|o| o.value().clone())
+            .collect()
+    }
+
+    pub fn open_count(&self) -> usize {
+        self.orders.iter().filter(|o| o.state.is_open()).count()
+    }
+
+    fn prune_terminal(&self) {
+        let cutoff = now() - Duration::minutes(TERMINAL_RETENTION_MINS);
+        self.orders
+            .retain(|_, o| o.state.is_open() || o.updated_at > cutoff);
+        self.oids.retain(|_, cloid| self.orders.contains_key(cloid));
+    }
+}
@@ -2,22 +2,39 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use alloy::signers::local::PrivateKeySigner;
+use async_trait::async_trait;
 use hyperliquid_rust_sdk::{
-    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient, ExchangeResponseStatus,
+    BaseUrl, ClientCancelRequest, ClientCancelRequestCloid, ClientLimit, ClientModifyRequest,
+    ClientOrder, ClientOrderRequest, ClientTrigger, ExchangeClient, ExchangeDataStatus,
+    ExchangeResponseStatus,
 };
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
 use serde::{Deserialize, Serialize};
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 use uuid::Uuid;
 
 use crate::errors::{AppError, AppResult};
+use crate::exchange::rate_limit::{INFO_WEIGHT, RequestBudget, action_weight};
+use crate::exchange::venue::{AckStatus, ExecutionVenue, OpenOrder, OrderAck, OrderRef};
+use crate::exchange::{AssetMetaCache, InfoService};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderSide {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl OrderSide {
+    pub fn opposite(&self) -> Self {
+        match self {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderTif {
     Gtc,
     Ioc,
@@ -34,40 +51,190 @@ impl OrderTif {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TriggerKind {
+    Tp,
+    Sl,
+}
+
+impl TriggerKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerKind::Tp => "tp",
+            TriggerKind::Sl => "sl",
+        }
+    }
+}
+
+/// Makes the order rest untriggered until the mark reaches `trigger_px`. Once
+/// triggered it executes as a market order when `is_market`, otherwise as a
+/// limit at the intent's `limit_px` (stop-limit).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderTrigger {
+    pub trigger_px: Decimal,
+    pub is_market: bool,
+    pub kind: TriggerKind,
+}
+
+impl OrderTrigger {
+    /// Stops fire when price moves against the order's side, take-profits
+    /// when it moves in favour of the position being closed.
+    pub fn fires(kind: TriggerKind, side: OrderSide, trigger_px: Decimal, price: Decimal) -> bool {
+        match (kind, side) {
+            (TriggerKind::Sl, OrderSide::Sell) | (TriggerKind::Tp, OrderSide::Buy) => {
+                price <= trigger_px
+            }
+            (TriggerKind::Sl, OrderSide::Buy) | (TriggerKind::Tp, OrderSide::Sell) => {
+                price >= trigger_px
+            }
+        }
+    }
+}
+
+/// Take-profit and stop-loss trigger prices attached to an entry. On the
+/// entry's first fill, reduce-only market triggers for the filled size are
+/// placed on the exchange and resized as more fills; when one leg fills the
+/// other is cancelled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TpSlBracket {
+    pub take_profit: Option<Decimal>,
+    pub stop_loss: Option<Decimal>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderIntent {
     pub asset: String,
     pub side: OrderSide,
-    pub size: String,
-    pub limit_px: String,
+    pub size: Decimal,
+    pub limit_px: Decimal,
     pub tif: OrderTif,
     pub reduce_only: bool,
     pub client_tag: String,
     pub cloid: Option<Uuid>,
+    #[serde(default)]
+    pub trigger: Option<OrderTrigger>,
+    #[serde(default)]
+    pub bracket: Option<TpSlBracket>,
 }
 
 impl OrderIntent {
+    /// A plain order with no tag, trigger or bracket. Size and price must both
+    /// be positive.
+    pub fn new(
+        asset: impl Into<String>,
+        side: OrderSide,
+        size: Decimal,
+        limit_px: Decimal,
+        tif: OrderTif,
+    ) -> AppResult<Self> {
+        let intent = Self {
+            asset: asset.into(),
+            side,
+            size,
+            limit_px,
+            tif,
+            reduce_only: false,
+            client_tag: String::new(),
+            cloid: None,
+            trigger: None,
+            bracket: None,
+        };
+        intent.validate()?;
+        Ok(intent)
+    }
+
+    pub fn with_reduce_only(mut self, reduce_only: bool) -> Self {
+        self.reduce_only = reduce_only;
+        self
+    }
+
+    pub fn with_tag(mut self, client_tag: impl Into<String>) -> Self {
+        self.client_tag = client_tag.into();
+        self
+    }
+
+    pub fn with_trigger(mut self, trigger: OrderTrigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    pub fn with_bracket(mut self, bracket: TpSlBracket) -> Self {
+        self.bracket = Some(bracket);
+        self
+    }
+
+    pub fn validate(&self) -> AppResult<()> {
+        if self.size <= Decimal::ZERO || self.limit_px <= Decimal::ZERO {
+            return Err(AppError::Config(format!(
+                "invalid order for {}: size {} at {}",
+                self.asset, self.size, self.limit_px
+            )));
+        }
+        if let Some(trigger) = &self.trigger
+            && trigger.trigger_px <= Decimal::ZERO
+        {
+            return Err(AppError::Config(format!(
+                "invalid trigger_px {} for {}",
+                trigger.trigger_px, self.asset
+            )));
+        }
+        Ok(())
+    }
+
     pub fn describe(&self) -> String {
         format!("{} {} {}", self.client_tag, self.asset, self.limit_px)
     }
 }
 
+/// The SDK takes `f64` and formats it again for signing. Intents are already
+/// rounded to exchange precision, so the conversion does not lose ticks.
+fn to_wire(value: Decimal) -> AppResult<f64> {
+    value
+        .to_f64()
+        .ok_or_else(|| AppError::Exchange(format!("{value} cannot be sent to the exchange")))
+}
+
+/// Hyperliquid echoes cloids back as 0x-prefixed hex, so everything that keys
+/// on a cloid uses this form.
+pub fn format_cloid(cloid: &Uuid) -> String {
+    format!("0x{}", cloid.simple())
+}
+
+pub fn parse_cloid(cloid: &str) -> Option<Uuid> {
+    Uuid::parse_str(cloid.trim_start_matches("0x")).ok()
+}
+
 #[derive(Clone)]
 pub struct OrderRouter {
     client: Arc<ExchangeClient>,
+    info: InfoService,
+    budget: Arc<RequestBudget>,
+    meta: Arc<AssetMetaCache>,
     wallet_address: alloy::primitives::Address,
 }
 
 impl OrderRouter {
-    pub async fn new(base_url: BaseUrl, signer_hex: &str) -> AppResult<Self> {
+    pub async fn new(
+        base_url: BaseUrl,
+        signer_hex: &str,
+        info: InfoService,
+        meta: Arc<AssetMetaCache>,
+    ) -> AppResult<Self> {
         let signer = PrivateKeySigner::from_str(signer_hex)
             .map_err(|e| AppError::Config(format!("invalid signer key: {e}")))?;
         let wallet_address = signer.address();
+        let budget = info.budget();
+        // the client loads exchange metadata on construction
+        budget.acquire(INFO_WEIGHT).await;
         let client = ExchangeClient::new(None, signer, Some(base_url), None, None)
             .await
             .map_err(|e| AppError::Exchange(e.to_string()))?;
         Ok(Self {
             client: Arc::new(client),
+            info,
+            budget,
+            meta,
             wallet_address,
         })
     }
@@ -76,48 +243,313 @@ impl OrderRouter {
         self.wallet_address
     }
 
+    fn build_request(intent: &OrderIntent, cloid: Uuid) -> AppResult<ClientOrderRequest> {
+        Ok(ClientOrderRequest {
+            asset: intent.asset.clone(),
+            is_buy: matches!(intent.side, OrderSide::Buy),
+            reduce_only: intent.reduce_only,
+            limit_px: to_wire(intent.limit_px)?,
+            sz: to_wire(intent.size)?,
+            order_type: match &intent.trigger {
+                Some(trigger) => ClientOrder::Trigger(ClientTrigger {
+                    is_market: trigger.is_market,
+                    trigger_px: to_wire(trigger.trigger_px)?,
+                    tpsl: trigger.kind.as_str().to_string(),
+                }),
+                None => ClientOrder::Limit(ClientLimit {
+                    tif: intent.tif.as_str().to_string(),
+                }),
+            },
+            cloid: Some(cloid),
+        })
+    }
+
+    fn check_statuses(response: ExchangeResponseStatus) -> AppResult<()> {
+        match response {
+            ExchangeResponseStatus::Ok(resp) => {
+                let statuses = resp.data.map(|d| d.statuses).unwrap_or_default();
+                for status in statuses {
+                    if let ExchangeDataStatus::Error(err) = status {
+                        return Err(AppError::Exchange(err));
+                    }
+                }
+                Ok(())
+            }
+            ExchangeResponseStatus::Err(err) => Err(AppError::Exchange(err.to_string())),
+        }
+    }
+
+    fn ack_status(status: ExchangeDataStatus) -> AckStatus {
+        match status {
+            ExchangeDataStatus::Resting(order) => AckStatus::Resting { oid: order.oid },
+            ExchangeDataStatus::Filled(order) => AckStatus::Filled {
+                oid: order.oid,
+                total_sz: order.total_sz.parse().unwrap_or_default(),
+                avg_px: order.avg_px.parse().unwrap_or_default(),
+            },
+            ExchangeDataStatus::Error(reason) => AckStatus::Rejected { reason },
+            _ => AckStatus::Pending,
+        }
+    }
+
+    /// Statuses come back positionally, one per order in the action.
+    fn statuses(response: ExchangeResponseStatus) -> AppResult<Vec<ExchangeDataStatus>> {
+        match response {
+            ExchangeResponseStatus::Ok(resp) => {
+                Ok(resp.data.map(|d| d.statuses).unwrap_or_default())
+            }
+            ExchangeResponseStatus::Err(err) => Err(AppError::Exchange(err.to_string())),
+        }
+    }
+
+    fn acks_from_response(
+        cloids: Vec<String>,
+        response: ExchangeResponseStatus,
+    ) -> AppResult<Vec<OrderAck>> {
+        let mut statuses = Self::statuses(response)?.into_iter();
+        Ok(cloids
+            .into_iter()
+            .map(|cloid| OrderAck {
+                cloid,
+                status: statuses
+                    .next()
+                    .map(Self::ack_status)
+                    .unwrap_or(AckStatus::Pending),
+            })
+            .collect())
+    }
+
+    fn cancel_results(
+        count: usize,
+        response: ExchangeResponseStatus,
+    ) -> AppResult<Vec<AppResult<()>>> {
+        let mut statuses = Self::statuses(response)?.into_iter();
+        Ok((0..count)
+            .map(|_| match statuses.next() {
+                Some(ExchangeDataStatus::Error(err)) => Err(AppError::Exchange(err)),
+                _ => Ok(()),
+            })
+            .collect())
+    }
+
+    fn log_rejections(acks: &[OrderAck]) {
+        for ack in acks {
+            if let AckStatus::Rejected { reason } = &ack.status {
+                warn!(cloid = %ack.cloid, reason = %reason, "order rejected by exchange");
+            }
+        }
+    }
+
     #[instrument(skip(self))]
-    pub async fn submit(&self, intent: OrderIntent) -> AppResult<String> {
+    pub async fn submit(&self, mut intent: OrderIntent) -> AppResult<OrderAck> {
+        self.meta.normalize(&mut intent)?;
         let cloid = intent.cloid.unwrap_or_else(Uuid::new_v4);
         info!(
             asset = %intent.asset,
             side = ?intent.side,
             tif = intent.tif.as_str(),
+            trigger = ?intent.trigger,
             reduce_only = intent.reduce_only,
             cloid = %cloid,
             "submitting order"
         );
 
-        let limit_px = intent
-            .limit_px
-            .parse::<f64>()
-            .map_err(|e| AppError::Config(format!("invalid limit_px: {e}")))?;
-        let sz = intent
-            .size
-            .parse::<f64>()
-            .map_err(|e| AppError::Config(format!("invalid size: {e}")))?;
-
-        let request = ClientOrderRequest {
-            asset: intent.asset.clone(),
-            is_buy: matches!(intent.side, OrderSide::Buy),
-            reduce_only: intent.reduce_only,
-            limit_px,
-            sz,
-            order_type: ClientOrder::Limit(ClientLimit {
-                tif: intent.tif.as_str().to_string(),
-            }),
-            cloid: Some(cloid),
-        };
+        let request = Self::build_request(&intent, cloid)?;
 
+        self.budget.acquire(action_weight(1)).await;
         let response = self
             .client
             .order(request, None)
             .await
             .map_err(|e| AppError::Exchange(e.to_string()))?;
 
-        match response {
-            ExchangeResponseStatus::Ok(_) => Ok(cloid.to_string()),
-            ExchangeResponseStatus::Err(err) => Err(AppError::Exchange(err.to_string())),
+        let acks = Self::acks_from_response(vec![format_cloid(&cloid)], response)?;
+        Self::log_rejections(&acks);
+        acks.into_iter()
+            .next()
+            .ok_or_else(|| AppError::Exchange("empty order response".into()))
+    }
+
+    /// Sends every intent in a single signed bulk order action. An intent that
+    /// fails normalization is acked as rejected on its own; the rest of the
+    /// batch is still sent.
+    #[instrument(skip_all, fields(orders = intents.len()))]
+    pub async fn submit_batch(&self, intents: &mut [OrderIntent]) -> AppResult<Vec<OrderAck>> {
+        let mut acks: Vec<Option<OrderAck>> = vec![None; intents.len()];
+        let mut slots = Vec::with_capacity(intents.len());
+        let mut cloids = Vec::with_capacity(intents.len());
+        let mut requests = Vec::with_capacity(intents.len());
+        for (idx, intent) in intents.iter_mut().enumerate() {
+            let cloid = *intent.cloid.get_or_insert_with(Uuid::new_v4);
+            let request = self
+                .meta
+                .normalize(intent)
+                .and_then(|_| Self::build_request(intent, cloid));
+            match request {
+                Ok(request) => {
+                    requests.push(request);
+                    cloids.push(format_cloid(&cloid));
+                    slots.push(idx);
+                }
+                Err(e) => {
+                    warn!(cloid = %cloid, intent = %intent.describe(), error = %e, "order rejected before submit");
+                    acks[idx] = Some(OrderAck {
+                        cloid: format_cloid(&cloid),
+                        status: AckStatus::Rejected {
+                            reason: e.to_string(),
+                        },
+                    });
+                }
+            }
+        }
+
+        if !requests.is_empty() {
+            info!(count = requests.len(), "submitting order batch");
+            self.budget.acquire(action_weight(requests.len())).await;
+            let response = self
+                .client
+                .bulk_order(requests, None)
+                .await
+                .map_err(|e| AppError::Exchange(e.to_string()))?;
+            let submitted = Self::acks_from_response(cloids, response)?;
+            Self::log_rejections(&submitted);
+            for (idx, ack) in slots.into_iter().zip(submitted) {
+                acks[idx] = Some(ack);
+            }
+        }
+        Ok(acks.into_iter().flatten().collect())
+    }
+
+    #[instrument(skip(self))]
+    pub async fn cancel(&self, asset: &str, order: OrderRef) -> AppResult<()> {
+        info!(asset, order = ?order, "cancelling order");
+        self.budget.acquire(action_weight(1)).await;
+        let response = match order {
+            OrderRef::Oid(oid) => {
+                self.client
+                    .cancel(
+                        ClientCancelRequest {
+                            asset: asset.to_string(),
+                            oid,
+                        },
+                        None,
+                    )
+                    .await
+            }
+            OrderRef::Cloid(cloid) => {
+                self.client
+                    .cancel_by_cloid(
+                        ClientCancelRequestCloid {
+                            asset: asset.to_string(),
+                            cloid,
+                        },
+                        None,
+                    )
+                    .await
+            }
+        }
+        .map_err(|e| AppError::Exchange(e.to_string()))?;
+        Self::check_statuses(response)
+    }
+
+    /// Oid and cloid cancels are separate exchange actions, so a mixed batch
+    /// costs two round-trips. Results are returned in request order.
+    #[instrument(skip_all, fields(orders = orders.len()))]
+    pub async fn cancel_batch(
+        &self,
+        orders: Vec<(String, OrderRef)>,
+    ) -> AppResult<Vec<AppResult<()>>> {
+        let mut results: Vec<AppResult<()>> = orders.iter().map(|_| Ok(())).collect();
+        let mut by_oid = (Vec::new(), Vec::new());
+        let mut by_cloid = (Vec::new(), Vec::new());
+        for (idx, (asset, order)) in orders.into_iter().enumerate() {
+            match order {
+                OrderRef::Oid(oid) => {
+                    by_oid.0.push(idx);
+                    by_oid.1.push(ClientCancelRequest { asset, oid });
+                }
+                OrderRef::Cloid(cloid) => {
+                    by_cloid.0.push(idx);
+                    by_cloid.1.push(ClientCancelRequestCloid { asset, cloid });
+                }
+            }
+        }
+        info!(
+            by_oid = by_oid.1.len(),
+            by_cloid = by_cloid.1.len(),
+            "cancelling order batch"
+        );
+
+        if !by_oid.1.is_empty() {
+            self.budget.acquire(action_weight(by_oid.1.len())).await;
+            let response = self
+                .client
+                .bulk_cancel(by_oid.1, None)
+                .await
+                .map_err(|e| AppError::Exchange(e.to_string()))?;
+            let outcomes = Self::cancel_results(by_oid.0.len(), response)?;
+            for (idx, outcome) in by_oid.0.into_iter().zip(outcomes) {
+                results[idx] = outcome;
+            }
         }
+        if !by_cloid.1.is_empty() {
+            self.budget.acquire(action_weight(by_cloid.1.len())).await;
+            let response = self
+                .client
+                .bulk_cancel_by_cloid(by_cloid.1, None)
+                .await
+                .map_err(|e| AppError::Exchange(e.to_string()))?;
+            let outcomes = Self::cancel_results(by_cloid.0.len(), response)?;
+            for (idx, outcome) in by_cloid.0.into_iter().zip(outcomes) {
+                results[idx] = outcome;
+            }
+        }
+        Ok(results)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn modify(&self, oid: u64, intent: &mut OrderIntent) -> AppResult<()> {
+        self.meta.normalize(intent)?;
+        let cloid = intent.cloid.unwrap_or_else(Uuid::new_v4);
+        info!(oid, asset = %intent.asset, limit_px = %intent.limit_px, size = %intent.size, "modifying order");
+        let order = Self::build_request(intent, cloid)?;
+        self.budget.acquire(action_weight(1)).await;
+        let response = self
+            .client
+            .modify(ClientModifyRequest { oid, order }, None)
+            .await
+            .map_err(|e| AppError::Exchange(e.to_string()))?;
+        Self::check_statuses(response)
+    }
+
+    pub async fn open_orders(&self) -> AppResult<Vec<OpenOrder>> {
+        self.info.open_orders(self.wallet_address).await
+    }
+}
+
+#[async_trait]
+impl ExecutionVenue for OrderRouter {
+    async fn submit(&self, intent: OrderIntent) -> AppResult<OrderAck> {
+        OrderRouter::submit(self, intent).await
+    }
+
+    async fn submit_batch(&self, intents: &mut [OrderIntent]) -> AppResult<Vec<OrderAck>> {
+        OrderRouter::submit_batch(self, intents).await
+    }
+
+    async fn cancel(&self, asset: &str, order: OrderRef) -> AppResult<()> {
+        OrderRouter::cancel(self, asset, order).await
+    }
+
+    async fn cancel_batch(&self, orders: Vec<(String, OrderRef)>) -> AppResult<Vec<AppResult<()>>> {
+        OrderRouter::cancel_batch(self, orders).await
+    }
+
+    async fn modify(&self, oid: u64, intent: &mut OrderIntent) -> AppResult<()> {
+        OrderRouter::modify(self, oid, intent).await
+    }
+
+    async fn open_orders(&self) -> AppResult<Vec<OpenOrder>> {
+        OrderRouter::open_orders(self).await
     }
 }
@@ -1,32 +1,95 @@
 use dashmap::DashMap;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub asset: String,
-    pub size: f64,
-    pub entry_price: f64,
+    pub size: Decimal,
+    pub entry_price: Decimal,
+    #[serde(default)]
+    pub realized_pnl: Decimal,
+    #[serde(default)]
+    pub fees: Decimal,
+}
+
+impl Position {
+    fn flat(asset: &str) -> Self {
+        Self {
+            asset: asset.to_string(),
+            size: Decimal::ZERO,
+            entry_price: Decimal::ZERO,
+            realized_pnl: Decimal::ZERO,
+            fees: Decimal::ZERO,
+        }
+    }
+
+    pub fn unrealized_pnl(&self, mark: Decimal) -> Decimal {
+        self.size * (mark - self.entry_price)
+    }
+
+    fn apply(&mut self, price: Decimal, signed_size: Decimal, fee: Decimal) {
+        let same_direction =
+            self.size.is_zero() || self.size.is_sign_negative() == signed_size.is_sign_negative();
+        if same_direction {
+            let new_size = self.size + signed_size;
+            if !new_size.is_zero() {
+                self.entry_price = (self.size.abs() * self.entry_price + signed_size.abs() * price)
+                    / new_size.abs();
+            }
+            self.size = new_size;
+        } else {
+            let closing = signed_size.abs().min(self.size.abs());
+            let per_unit = if self.size.is_sign_negative() {
+                self.entry_price - price
+            } else {
+                price - self.entry_price
+            };
+            self.realized_pnl += closing * per_unit;
+            let new_size = self.size + signed_size;
+            if new_size.is_zero() {
+                self.size = Decimal::ZERO;
+                self.entry_price = Decimal::ZERO;
+            } else {
+                // the remainder past zero opens a fresh position at the fill price
+                if new_size.is_sign_negative() != self.size.is_sign_negative() {
+                    self.entry_price = price;
+                }
+                self.size = new_size;
+            }
+        }
+
+        self.fees += fee;
+        self.realized_pnl -= fee;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FillEvent {
     pub asset: String,
-    pub price: f64,
-    pub size: f64,
+    pub price: Decimal,
+    pub size: Decimal,
     pub is_buy: bool,
     pub cloid: Option<String>,
+    #[serde(default)]
+    pub fee: Decimal,
+    /// Exchange trade id; `None` for simulated fills.
+    #[serde(default)]
+    pub tid: Option<u64>,
 }
 
 #[derive(Clone, Default)]
 pub struct PositionManager {
     inner: DashMap<String, Position>,
+    marks: DashMap<String, Decimal>,
 }
 
 impl PositionManager {
     pub fn new() -> Self {
         Self {
             inner: DashMap::new(),
+            marks: DashMap::new(),
         }
     }
 
@@ -34,22 +97,124 @@ impl PositionManager {
         self.inner.iter().map(|p| p.value().clone()).collect()
     }
 
+    /// Replaces tracked positions with a saved snapshot.
+    pub fn restore(&self, positions: Vec<Position>) {
+        self.inner.clear();
+        for position in positions {
+            self.inner.insert(position.asset.clone(), position);
+        }
+    }
+
+    pub fn position(&self, asset: &str) -> Option<Position> {
+        self.inner.get(asset).map(|p| p.value().clone())
+    }
+
+    pub fn update_mark(&self, asset: &str, price: Decimal) {
+        self.marks.insert(asset.to_string(), price);
+    }
+
+    pub fn mark(&self, asset: &str) -> Option<Decimal> {
+        self.marks.get(asset).map(|m| *m)
+    }
+
+    /// Overwrites size and entry from an authoritative source, keeping the
+    /// locally accumulated realized PnL and fees.
+    pub fn sync_position(&self, asset: &str, size: Decimal, entry_price: Decimal) {
+        let mut position = self
+            .inner
+            .entry(asset.to_string())
+            .or_insert_with(|| Position::flat(asset));
+        position.size = size;
+        position.entry_price = if size.is_zero() {
+            Decimal::ZERO
+        } else {
+            entry_price
+        };
+    }
+
     #[instrument(skip(self))]
     pub fn apply_fill(&self, fill: &FillEvent) {
+        let signed_size = if fill.is_buy { fill.size } else { -fill.size };
         self.inner
             .entry(fill.asset.clone())
-            .and_modify(|position| {
-                if fill.is_buy {
-                    position.size += fill.size;
-                    position.entry_price = fill.price;
-                } else {
-                    position.size -= fill.size;
-                }
-            })
-            .or_insert(Position {
-                asset: fill.asset.clone(),
-                size: if fill.is_buy { fill.size } else { -fill.size },
-                entry_price: fill.price,
-            });
+            .or_insert_with(|| Position::flat(&fill.asset))
+            .apply(fill.price, signed_size, fill.fee);
+    }
+
+    /// Realized PnL net of fees.
+    pub fn realized_pnl(&self, asset: &str) -> Decimal {
+        self.inner
+            .get(asset)
+            .map(|p| p.realized_pnl)
+            .unwrap_or_default()
+    }
+
+    /// Unrealized PnL against the last mark; zero when no mark is known yet.
+    pub fn unrealized_pnl(&self, asset: &str) -> Decimal {
+        match (self.inner.get(asset), self.mark(asset)) {
+            (Some(position), Some(mark)) => position.unrealized_pnl(mark),
+            _ => Decimal::ZERO,
+        }
+    }
+
+    pub fn total_realized_pnl(&self) -> Decimal {
+        self.inner.iter().map(|p| p.realized_pnl).sum()
+    }
+
+    pub fn total_unrealized_pnl(&self) -> Decimal {
+        self.inner
+            .iter()
+            .filter_map(|p| self.mark(p.key()).map(|mark| p.unrealized_pnl(mark)))
+            .sum()
+    }
+
+    pub fn total_pnl(&self) -> Decimal {
+        self.total_realized_pnl() + self.total_unrealized_pnl()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adding_averages_the_entry() {
+        let mut position = Position::flat("BTC");
+        position.apply(Decimal::from(100), Decimal::ONE, Decimal::ZERO);
+        position.apply(Decimal::from(110), Decimal::ONE, Decimal::ZERO);
+        assert_eq!(position.size, Decimal::TWO);
+        assert_eq!(position.entry_price, Decimal::from(105));
+        assert_eq!(position.realized_pnl, Decimal::ZERO);
+    }
+
+    #[test]
+    fn partial_close_realizes_pnl_net_of_fees() {
+        let mut position = Position::flat("BTC");
+        position.apply(Decimal::from(100), Decimal::TWO, Decimal::ZERO);
+        position.apply(Decimal::from(120), Decimal::NEGATIVE_ONE, Decimal::ONE);
+        assert_eq!(position.size, Decimal::ONE);
+        assert_eq!(position.entry_price, Decimal::from(100));
+        assert_eq!(position.realized_pnl, Decimal::from(19));
+        assert_eq!(position.fees, Decimal::ONE);
+    }
+
+    #[test]
+    fn full_close_resets_the_entry() {
+        let mut position = Position::flat("BTC");
+        position.apply(Decimal::from(100), Decimal::from(-3), Decimal::ZERO);
+        position.apply(Decimal::from(90), Decimal::from(3), Decimal::ZERO);
+        assert!(position.size.is_zero());
+        assert!(position.entry_price.is_zero());
+        assert_eq!(position.realized_pnl, Decimal::from(30));
+    }
+
+    #[test]
+    fn flip_opens_the_remainder_at_the_fill_price() {
+        let mut position = Position::flat("BTC");
+        position.apply(Decimal::from(100), Decimal::ONE, Decimal::ZERO);
+        position.apply(Decimal::from(90), Decimal::from(-3), Decimal::ZERO);
+        assert_eq!(position.size, Decimal::from(-2));
+        assert_eq!(position.entry_price, Decimal::from(90));
+        assert_eq!(position.realized_pnl, Decimal::from(-10));
     }
 }
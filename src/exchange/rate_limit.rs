@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+use tokio::time::{Instant, sleep};
+use tracing::{debug, warn};
+
+/// Hyperliquid REST weights. Light info requests (`allMids`, `l2Book`,
+/// `clearinghouseState`, ...) cost 2, other info requests 20.
+pub const INFO_LIGHT_WEIGHT: u32 = 2;
+pub const INFO_WEIGHT: u32 = 20;
+
+const SLOW_WAIT: Duration = Duration::from_secs(1);
+
+/// Exchange actions cost 1 plus 1 per 40 orders or cancels in the batch.
+pub fn action_weight(batch_len: usize) -> u32 {
+    1 + (batch_len / 40) as u32
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BudgetStats {
+    pub throttled: u64,
+    pub waited: Duration,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// Token bucket shared by every REST caller. Callers queue in order on the
+/// bucket lock, so a throttled request holds back the ones behind it instead
+/// of letting them race for tokens.
+pub struct RequestBudget {
+    bucket: Mutex<Bucket>,
+    capacity: f64,
+    per_sec: f64,
+    throttled: AtomicU64,
+    waited_ms: AtomicU64,
+}
+
+impl RequestBudget {
+    /// `weight_per_minute` of zero disables limiting.
+    pub fn per_minute(weight_per_minute: u32) -> Self {
+        let capacity = weight_per_minute as f64;
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                refilled_at: Instant::now(),
+            }),
+            capacity,
+            per_sec: capacity / 60.0,
+            throttled: AtomicU64::new(0),
+            waited_ms: AtomicU64::new(0),
+        }
+    }
+
+    pub async fn acquire(&self, weight: u32) {
+        if self.capacity <= 0.0 {
+            return;
+        }
+        // a single request heavier than the bucket would otherwise never run
+        let weight = (weight as f64).min(self.capacity);
+        let mut bucket = self.bucket.lock().await;
+        self.refill(&mut bucket);
+        if bucket.tokens < weight {
+            let wait = Duration::from_secs_f64((weight - bucket.tokens) / self.per_sec);
+            let wait_ms = wait.as_millis() as u64;
+            // running totals ride along so the metric is visible while running
+            let throttled = self.throttled.fetch_add(1, Ordering::Relaxed) + 1;
+            let waited_ms = self.waited_ms.fetch_add(wait_ms, Ordering::Relaxed) + wait_ms;
+            if wait >= SLOW_WAIT {
+                warn!(
+                    wait_ms,
+                    weight, throttled, waited_ms, "request budget exhausted, throttling"
+                );
+            } else {
+                debug!(wait_ms, weight, throttled, waited_ms, "throttling request");
+            }
+            sleep(wait).await;
+            self.refill(&mut bucket);
+        }
+        bucket.tokens -= weight;
+    }
+
+    pub fn stats(&self) -> BudgetStats {
+        BudgetStats {
+            throttled: self.throttled.load(Ordering::Relaxed),
+            waited: Duration::from_millis(self.waited_ms.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.capacity);
+        bucket.refilled_at = now;
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::Address;
+use chrono::Duration as ChronoDuration;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tracing::{error, info, instrument, warn};
+
+use crate::config::{ReconcilePolicy, ReconciliationConfig};
+use crate::errors::{AppError, AppResult};
+use crate::exchange::{InfoService, OrderManager, PositionManager};
+use crate::storage::journal::Journal;
+use crate::utils::time::now;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionDrift {
+    pub asset: String,
+    pub local_size: Decimal,
+    pub exchange_size: Decimal,
+    pub exchange_entry: Decimal,
+}
+
+/// Orders updated more recently than this may still have a fill or cancel in
+/// flight and are not reported as stale.
+const ORDER_GRACE_SECS: i64 = 5;
+
+/// Locally open order the exchange no longer lists.
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleOrder {
+    pub cloid: String,
+    pub oid: u64,
+    pub asset: String,
+}
+
+/// Compares `PositionManager` and `OrderManager` with the exchange clearinghouse state
+/// and open orders.
+pub struct Reconciler {
+    info: InfoService,
+    address: Address,
+    config: ReconciliationConfig,
+    journal: Arc<Journal>,
+}
+
+impl Reconciler {
+    pub fn new(
+        info: InfoService,
+        address: Address,
+        config: ReconciliationConfig,
+        journal: Arc<Journal>,
+    ) -> Self {
+        Self {
+            info,
+            address,
+            config,
+            journal,
+        }
+    }
+
+    pub fn interval(&self) -> Option<Duration> {
+        (self.config.interval_secs > 0).then(|| Duration::from_secs(self.config.interval_secs))
+    }
+
+    /// Startup load: the exchange is authoritative regardless of policy.
+    #[instrument(skip_all)]
+    pub async fn seed(&self, positions: &PositionManager) -> AppResult<()> {
+        let remote = self.info.positions(self.address).await?;
+        for position in &remote {
+            info!(
+                asset = %position.asset,
+                size = %position.size,
+                entry = %position.entry_price,
+                "seeding position from exchange"
+            );
+            positions.sync_position(&position.asset, position.size, position.entry_price);
+        }
+
+        let open_orders = self.info.open_orders(self.address).await?;
+        if !open_orders.is_empty() {
+            warn!(
+                count = open_orders.len(),
+                "account has resting orders at startup"
+            );
+        }
+        self.record(serde_json::json!({
+            "type": "reconcile_seed",
+            "positions": remote,
+            "open_orders": open_orders,
+        }));
+        Ok(())
+    }
+
+    /// Returns the positions it overwrote under the resync policy, and an
+    /// error only when drift is found under the halt policy; fetch failures
+    /// are logged and retried on the next tick.
+    #[instrument(skip_all)]
+    pub async fn reconcile(
+        &self,
+        positions: &PositionManager,
+        orders: &OrderManager,
+    ) -> AppResult<Vec<PositionDrift>> {
+        let (drifts, stale) = match self.fetch_drift(positions, orders).await {
+            Ok(found) => found,
+            Err(e) => {
+                warn!(error = %e, "reconciliation fetch failed");
+                return Ok(Vec::new());
+            }
+        };
+        if drifts.is_empty() && stale.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        for drift in &drifts {
+            warn!(
+                asset = %drift.asset,
+                local = %drift.local_size,
+                exchange = %drift.exchange_size,
+                policy = ?self.config.policy,
+                "position drift detected"
+            );
+        }
+        for order in &stale {
+            warn!(
+                cloid = %order.cloid,
+                oid = order.oid,
+                asset = %order.asset,
+                policy = ?self.config.policy,
+                "tracked order no longer open on exchange"
+            );
+        }
+        self.record(serde_json::json!({
+            "type": "reconcile_drift",
+            "policy": self.config.policy,
+            "drifts": drifts,
+            "stale_orders": stale,
+        }));
+
+        match self.config.policy {
+            ReconcilePolicy::Warn => Ok(Vec::new()),
+            ReconcilePolicy::Resync => {
+                for drift in &drifts {
+                    positions.sync_position(
+                        &drift.asset,
+                        drift.exchange_size,
+                        drift.exchange_entry,
+                    );
+                }
+                for order in &stale {
+                    orders.mark_cancelled(&order.cloid);
+                }
+                Ok(drifts)
+            }
+            ReconcilePolicy::Halt => {
+                error!(
+                    assets = drifts.len(),
+                    orders = stale.len(),
+                    "halting on reconciliation drift"
+                );
+                Err(AppError::Exchange(format!(
+                    "drift on {} position(s) and {} order(s)",
+                    drifts.len(),
+                    stale.len()
+                )))
+            }
+        }
+    }
+
+    async fn fetch_drift(
+        &self,
+        positions: &PositionManager,
+        orders: &OrderManager,
+    ) -> AppResult<(Vec<PositionDrift>, Vec<StaleOrder>)> {
+        Ok((
+            self.drift(positions).await?,
+            self.stale_orders(orders).await?,
+        ))
+    }
+
+    async fn stale_orders(&self, orders: &OrderManager) -> AppResult<Vec<StaleOrder>> {
+        let remote: HashSet<u64> = self
+            .info
+            .open_orders(self.address)
+            .await?
+            .into_iter()
+            .map(|o| o.oid)
+            .collect();
+        let cutoff = now() - ChronoDuration::seconds(ORDER_GRACE_SECS);
+        Ok(orders
+            .open_orders(None)
+            .into_iter()
+            .filter(|o| o.updated_at < cutoff)
+            .filter_map(|o| {
+                let oid = o.oid?;
+                (!remote.contains(&oid)).then_some(StaleOrder {
+                    cloid: o.cloid,
+                    oid,
+                    asset: o.asset,
+                })
+            })
+            .collect())
+    }
+
+    async fn drift(&self, positions: &PositionManager) -> AppResult<Vec<PositionDrift>> {
+        let remote: HashMap<String, (Decimal, Decimal)> = self
+            .info
+            .positions(self.address)
+            .await?
+            .into_iter()
+            .map(|p| (p.asset, (p.size, p.entry_price)))
+            .collect();
+        let local: HashMap<String, Decimal> = positions
+            .snapshot()
+            .into_iter()
+            .map(|p| (p.asset, p.size))
+            .collect();
+
+        let assets: BTreeSet<&String> = remote.keys().chain(local.keys()).collect();
+        Ok(assets
+            .into_iter()
+            .filter_map(|asset| {
+                let local_size = local.get(asset).copied().unwrap_or_default();
+                let (exchange_size, exchange_entry) =
+                    remote.get(asset).copied().unwrap_or_default();
+                ((local_size - exchange_size).abs() > self.config.size_tolerance).then(|| {
+                    PositionDrift {
+                        asset: asset.clone(),
+                        local_size,
+                        exchange_size,
+                        exchange_entry,
+                    }
+                })
+            })
+            .collect())
+    }
+
+    fn record(&self, record: serde_json::Value) {
+        if let Err(e) = self.journal.append(&record) {
+            warn!(error = %e, "failed to journal reconciliation");
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use rust_decimal::Decimal;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::errors::{AppError, AppResult};
+use crate::exchange::order_router::{
+    OrderIntent, OrderSide, OrderTif, OrderTrigger, TriggerKind, format_cloid,
+};
+use crate::exchange::venue::{AckStatus, ExecutionVenue, OpenOrder, OrderAck, OrderRef};
+use crate::exchange::{AssetMetaCache, FillEvent, PositionManager};
+use crate::marketdata::events::MarketEvent;
+
+#[derive(Debug, Clone)]
+struct SimTrigger {
+    px: Decimal,
+    is_market: bool,
+    kind: TriggerKind,
+}
+
+/// The forming candle as of some moment.
+#[derive(Debug, Clone)]
+struct SimBar {
+    open_time: DateTime<Utc>,
+    low: Decimal,
+    high: Decimal,
+}
+
+#[derive(Debug, Clone)]
+struct SimOrder {
+    oid: u64,
+    cloid: String,
+    asset: String,
+    side: OrderSide,
+    size: Decimal,
+    limit_px: Decimal,
+    tif: OrderTif,
+    reduce_only: bool,
+    trigger: Option<SimTrigger>,
+    /// Bar that was forming when the order was placed.
+    placed_in: Option<SimBar>,
+}
+
+impl SimOrder {
+    fn from_intent(oid: u64, cloid: String, intent: &OrderIntent) -> Self {
+        Self {
+            oid,
+            cloid,
+            asset: intent.asset.clone(),
+            side: intent.side,
+            size: intent.size,
+            limit_px: intent.limit_px,
+            tif: intent.tif,
+            reduce_only: intent.reduce_only,
+            trigger: intent.trigger.as_ref().map(|trigger| SimTrigger {
+                px: trigger.trigger_px,
+                is_market: trigger.is_market,
+                kind: trigger.kind,
+            }),
+            placed_in: None,
+        }
+    }
+
+    fn fires(&self, price: Decimal) -> bool {
+        self.trigger
+            .as_ref()
+            .is_some_and(|t| OrderTrigger::fires(t.kind, self.side, t.px, price))
+    }
+
+    fn crosses(&self, price: Decimal) -> bool {
+        match self.side {
+            OrderSide::Buy => price <= self.limit_px,
+            OrderSide::Sell => price >= self.limit_px,
+        }
+    }
+
+    /// Price range covered since placement. A bar that was already forming
+    /// then only contributes the extremes it made afterwards, plus its close.
+    fn range_since(&self, bar: &SimBar, close: Decimal) -> (Decimal, Decimal) {
+        match &self.placed_in {
+            Some(placed) if placed.open_time == bar.open_time => (
+                if bar.low < placed.low { bar.low } else { close },
+                if bar.high > placed.high {
+                    bar.high
+                } else {
+                    close
+                },
+            ),
+            _ => (bar.low, bar.high),
+        }
+    }
+
+    /// Whether any price in `low..=high` touches the order.
+    fn touched(&self, low: Decimal, high: Decimal) -> bool {
+        match self.trigger {
+            Some(_) => self.fires(low) || self.fires(high),
+            None => match self.side {
+                OrderSide::Buy => self.crosses(low),
+                OrderSide::Sell => self.crosses(high),
+            },
+        }
+    }
+
+    /// Size this order may fill against `position`; a reduce-only order is
+    /// capped at the position and gets nothing once it would add to it.
+    fn fillable(&self, position: Decimal) -> Decimal {
+        if !self.reduce_only {
+            return self.size;
+        }
+        match self.side {
+            OrderSide::Buy if position < Decimal::ZERO => self.size.min(-position),
+            OrderSide::Sell if position > Decimal::ZERO => self.size.min(position),
+            _ => Decimal::ZERO,
+        }
+    }
+
+    fn fill(&self, price: Decimal) -> FillEvent {
+        FillEvent {
+            asset: self.asset.clone(),
+            price,
+            size: self.size,
+            is_buy: matches!(self.side, OrderSide::Buy),
+            cloid: Some(self.cloid.clone()),
+            fee: Decimal::ZERO,
+            tid: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct SimBook {
+    marks: HashMap<String, Decimal>,
+    bars: HashMap<String, SimBar>,
+    positions: HashMap<String, Decimal>,
+    resting: Vec<SimOrder>,
+}
+
+/// In-process venue: IOC and GTC orders that cross the last mark fill at the
+/// mark, resting GTC/ALO orders fill at their limit once the price trades
+/// through them. Trigger orders rest until the price reaches the trigger, then
+/// fill (market) or are placed as a limit order. Candles are checked against
+/// the high and low made since each order was placed, not just the close.
+/// Reduce-only orders never fill more
+/// than the simulated position and are rejected when they would add to it.
+/// Fills are pushed to the receiver returned by `new`.
+pub struct SimulatedVenue {
+    book: Mutex<SimBook>,
+    next_oid: AtomicU64,
+    fills: UnboundedSender<FillEvent>,
+    meta: Option<Arc<AssetMetaCache>>,
+    positions: Option<Arc<PositionManager>>,
+}
+
+impl SimulatedVenue {
+    pub fn new() -> (Self, UnboundedReceiver<FillEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let venue = Self {
+            book: Mutex::new(SimBook::default()),
+            next_oid: AtomicU64::new(1),
+            fills: tx,
+            meta: None,
+            positions: None,
+        };
+        (venue, rx)
+    }
+
+    /// Applies the exchange's rounding and minimum notional rules, so paper
+    /// trading rejects what live trading would.
+    pub fn with_meta(mut self, meta: Arc<AssetMetaCache>) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Seeds each asset's simulated position from `positions` the first time
+    /// it trades, so restored positions can be reduced.
+    pub fn with_positions(mut self, positions: Arc<PositionManager>) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    pub fn mark(&self, asset: &str) -> Option<Decimal> {
+        self.book.lock().marks.get(asset).copied()
+    }
+
+    pub fn on_market_event(&self, event: &MarketEvent) {
+        match event {
+            MarketEvent::Candle(candle) => {
+                let bar = SimBar {
+                    open_time: candle.open_time,
+                    low: candle.low,
+                    high: candle.high,
+                };
+                self.sweep(&candle.asset, Some(bar), candle.close)
+            }
+            _ => {
+                if let Some(price) = event.price() {
+                    self.update_mark(event.asset(), price);
+                }
+            }
+        }
+    }
+
+    pub fn update_mark(&self, asset: &str, price: Decimal) {
+        self.sweep(asset, None, price);
+    }
+
+    /// Sets the mark and executes every resting order the price reached since
+    /// it was placed: the bar's range when there is one, otherwise just
+    /// `mark`. Market triggers fill at their trigger price, clamped into that
+    /// range.
+    #[instrument(skip(self))]
+    fn sweep(&self, asset: &str, bar: Option<SimBar>, mark: Decimal) {
+        let range = |order: &SimOrder| match &bar {
+            Some(bar) => order.range_since(bar, mark),
+            None => (mark, mark),
+        };
+        let (filled, triggered) = {
+            let mut book = self.book.lock();
+            book.marks.insert(asset.to_string(), mark);
+            if let Some(bar) = &bar {
+                book.bars.insert(asset.to_string(), bar.clone());
+            }
+            let (touched, resting): (Vec<_>, Vec<_>) = std::mem::take(&mut book.resting)
+                .into_iter()
+                .partition(|order| {
+                    let (low, high) = range(order);
+                    order.asset == asset && order.touched(low, high)
+                });
+            book.resting = resting;
+            touched
+                .into_iter()
+                .partition::<Vec<_>, _>(|order| order.trigger.is_none())
+        };
+
+        for order in filled {
+            debug!(
+                oid = order.oid,
+                price = %order.limit_px,
+                "resting order filled"
+            );
+            let price = order.limit_px;
+            self.execute(order, price);
+        }
+        for order in triggered {
+            let (low, high) = range(&order);
+            let price = order
+                .trigger
+                .as_ref()
+                .map_or(mark, |t| t.px.clamp(low, high));
+            debug!(oid = order.oid, %price, "trigger order fired");
+            self.execute_triggered(order, price);
+        }
+    }
+
+    fn execute_triggered(&self, mut order: SimOrder, price: Decimal) {
+        let Some(trigger) = order.trigger.take() else {
+            return;
+        };
+        if trigger.is_market {
+            self.execute(order, price);
+        } else {
+            self.place(order);
+        }
+    }
+
+    /// Fills what the position allows and returns the filled size.
+    fn execute(&self, mut order: SimOrder, price: Decimal) -> Decimal {
+        let size = {
+            let mut book = self.book.lock();
+            let position = self.position_mut(&mut book, &order.asset);
+            let size = order.fillable(*position);
+            match order.side {
+                OrderSide::Buy => *position += size,
+                OrderSide::Sell => *position -= size,
+            }
+            size
+        };
+        if size.is_zero() {
+            info!(cloid = %order.cloid, "reduce-only order has no position left to reduce, cancelled");
+            return size;
+        }
+        order.size = size;
+        self.emit(order.fill(price));
+        size
+    }
+
+    fn emit(&self, fill: FillEvent) {
+        if self.fills.send(fill).is_err() {
+            warn!("simulated fill dropped, receiver closed");
+        }
+    }
+
+    fn position(&self, asset: &str) -> Decimal {
+        *self.position_mut(&mut self.book.lock(), asset)
+    }
+
+    fn position_mut<'a>(&self, book: &'a mut SimBook, asset: &str) -> &'a mut Decimal {
+        book.positions.entry(asset.to_string()).or_insert_with(|| {
+            self.positions
+                .as_ref()
+                .and_then(|positions| positions.position(asset))
+                .map_or(Decimal::ZERO, |position| position.size)
+        })
+    }
+
+    fn place(&self, mut order: SimOrder) -> AckStatus {
+        order.placed_in = self.book.lock().bars.get(&order.asset).cloned();
+        if order.fillable(self.position(&order.asset)).is_zero() {
+            warn!(cloid = %order.cloid, "reduce-only order would increase position, rejected");
+            return AckStatus::Rejected {
+                reason: "reduce-only order would increase position".into(),
+            };
+        }
+        let mark = self.mark(&order.asset);
+        if order.trigger.is_some() {
+            let oid = order.oid;
+            match mark {
+                Some(px) if order.fires(px) => self.execute_triggered(order, px),
+                _ => self.book.lock().resting.push(order),
+            }
+            return AckStatus::Resting { oid };
+        }
+        let crosses = mark.is_some_and(|px| order.crosses(px));
+
+        match (order.tif, crosses, mark) {
+            (OrderTif::Ioc | OrderTif::Gtc, true, Some(px)) => {
+                let oid = order.oid;
+                let total_sz = self.execute(order, px);
+                AckStatus::Filled {
+                    oid,
+                    total_sz,
+                    avg_px: px,
+                }
+            }
+            (OrderTif::Ioc, _, _) => {
+                info!(cloid = %order.cloid, "ioc order did not match, cancelled");
+                AckStatus::Rejected {
+                    reason: "ioc order could not immediately match".into(),
+                }
+            }
+            (OrderTif::Alo, true, _) => {
+                warn!(cloid = %order.cloid, "post-only order would have matched, rejected");
+                AckStatus::Rejected {
+                    reason: "post-only order would have immediately matched".into(),
+                }
+            }
+            _ => {
+                let oid = order.oid;
+                self.book.lock().resting.push(order);
+                AckStatus::Resting { oid }
+            }
+        }
+    }
+}
+
+impl SimulatedVenue {
+    fn normalize(&self, intent: &mut OrderIntent) -> AppResult<()> {
+        match &self.meta {
+            Some(meta) => meta.normalize(intent),
+            None => Ok(()),
+        }
+    }
+
+    fn submit_normalized(&self, intent: &OrderIntent) -> OrderAck {
+        let cloid = format_cloid(&intent.cloid.unwrap_or_else(Uuid::new_v4));
+        let order = SimOrder::from_intent(
+            self.next_oid.fetch_add(1, Ordering::Relaxed),
+            cloid.clone(),
+            intent,
+        );
+        info!(
+            asset = %intent.asset,
+            side = ?intent.side,
+            oid = order.oid,
+            cloid = %cloid,
+            "simulated order submitted"
+        );
+        let status = self.place(order);
+        OrderAck { cloid, status }
+    }
+}
+
+#[async_trait]
+impl ExecutionVenue for SimulatedVenue {
+    async fn submit(&self, mut intent: OrderIntent) -> AppResult<OrderAck> {
+        self.normalize(&mut intent)?;
+        Ok(self.submit_normalized(&intent))
+    }
+
+    async fn submit_batch(&self, intents: &mut [OrderIntent]) -> AppResult<Vec<OrderAck>> {
+        Ok(intents
+            .iter_mut()
+            .map(|intent| {
+                let cloid = format_cloid(intent.cloid.get_or_insert_with(Uuid::new_v4));
+                match self.normalize(intent) {
+                    Ok(()) => self.submit_normalized(intent),
+                    Err(e) => {
+                        warn!(cloid, intent = %intent.describe(), error = %e, "order rejected before submit");
+                        OrderAck {
+                            cloid,
+                            status: AckStatus::Rejected {
+                                reason: e.to_string(),
+                            },
+                        }
+                    }
+                }
+            })
+            .collect())
+    }
+
+    async fn cancel(&self, asset: &str, order: OrderRef) -> AppResult<()> {
+        let mut book = self.book.lock();
+        let position = book.resting.iter().position(|o| {
+            o.asset == asset
+                && match &order {
+                    OrderRef::Oid(oid) => o.oid == *oid,
+                    OrderRef::Cloid(cloid) => o.cloid == format_cloid(cloid),
+                }
+        });
+        match position {
+            Some(idx) => {
+                book.resting.remove(idx);
+                Ok(())
+            }
+            None => Err(AppError::Exchange(format!(
+                "order {order:?} not resting on {asset}"
+            ))),
+        }
+    }
+
+    async fn modify(&self, oid: u64, intent: &mut OrderIntent) -> AppResult<()> {
+        self.normalize(intent)?;
+        let order = {
+            let mut book = self.book.lock();
+            let idx = book
+                .resting
+                .iter()
+                .position(|o| o.oid == oid)
+                .ok_or_else(|| AppError::Exchange(format!("order {oid} not resting")))?;
+            let cloid = book.resting[idx].cloid.clone();
+            let order = SimOrder::from_intent(oid, cloid, intent);
+            book.resting.remove(idx);
+            order
+        };
+        if let AckStatus::Rejected { reason } = self.place(order) {
+            return Err(AppError::Exchange(reason));
+        }
+        Ok(())
+    }
+
+    async fn open_orders(&self) -> AppResult<Vec<OpenOrder>> {
+        Ok(self
+            .book
+            .lock()
+            .resting
+            .iter()
+            .map(|o| OpenOrder {
+                asset: o.asset.clone(),
+                side: o.side,
+                size: o.size,
+                limit_px: o.limit_px,
+                oid: o.oid,
+                cloid: Some(o.cloid.clone()),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::order_router::OrderTrigger;
+    use crate::marketdata::events::CandleEvent;
+    use chrono::TimeZone;
+
+    fn px(value: i64) -> Decimal {
+        Decimal::from(value)
+    }
+
+    /// One minute candle for BTC opening `minute` minutes into the session.
+    fn candle(minute: i64, low: i64, high: i64, close: i64) -> MarketEvent {
+        let open_time = Utc.timestamp_opt(1_700_000_000 + minute * 60, 0).unwrap();
+        MarketEvent::Candle(CandleEvent {
+            asset: "BTC".into(),
+            open: px(close),
+            high: px(high),
+            low: px(low),
+            close: px(close),
+            volume: Decimal::ZERO,
+            trades: 0,
+            open_time,
+            timestamp: open_time,
+            interval: "1m".into(),
+            closed: true,
+        })
+    }
+
+    fn limit(side: OrderSide, size: i64, price: i64, tif: OrderTif) -> OrderIntent {
+        OrderIntent::new("BTC", side, px(size), px(price), tif).unwrap()
+    }
+
+    fn stop_loss(size: i64, trigger_px: i64) -> OrderIntent {
+        limit(OrderSide::Sell, size, trigger_px, OrderTif::Gtc)
+            .with_reduce_only(true)
+            .with_trigger(OrderTrigger {
+                trigger_px: px(trigger_px),
+                is_market: true,
+                kind: TriggerKind::Sl,
+            })
+    }
+
+    /// Venue holding a one lot long opened at 100.
+    async fn long_venue() -> (SimulatedVenue, UnboundedReceiver<FillEvent>) {
+        let (venue, mut fills) = SimulatedVenue::new();
+        venue.update_mark("BTC", px(100));
+        let ack = venue
+            .submit(limit(OrderSide::Buy, 1, 101, OrderTif::Ioc))
+            .await
+            .unwrap();
+        assert!(matches!(ack.status, AckStatus::Filled { .. }));
+        fills.try_recv().unwrap();
+        (venue, fills)
+    }
+
+    #[tokio::test]
+    async fn crossing_order_fills_at_the_mark() {
+        let (venue, mut fills) = SimulatedVenue::new();
+        venue.update_mark("BTC", px(100));
+        let ack = venue
+            .submit(limit(OrderSide::Buy, 1, 105, OrderTif::Gtc))
+            .await
+            .unwrap();
+        assert!(matches!(ack.status, AckStatus::Filled { avg_px, .. } if avg_px == px(100)));
+        assert_eq!(fills.try_recv().unwrap().price, px(100));
+    }
+
+    #[tokio::test]
+    async fn ioc_without_a_match_is_rejected() {
+        let (venue, _fills) = SimulatedVenue::new();
+        venue.update_mark("BTC", px(100));
+        let ack = venue
+            .submit(limit(OrderSide::Buy, 1, 95, OrderTif::Ioc))
+            .await
+            .unwrap();
+        assert!(ack.is_rejected());
+    }
+
+    #[tokio::test]
+    async fn crossing_post_only_is_rejected() {
+        let (venue, _fills) = SimulatedVenue::new();
+        venue.update_mark("BTC", px(100));
+        let ack = venue
+            .submit(limit(OrderSide::Sell, 1, 95, OrderTif::Alo))
+            .await
+            .unwrap();
+        assert!(ack.is_rejected());
+    }
+
+    #[tokio::test]
+    async fn resting_order_fills_on_a_candle_wick() {
+        let (venue, mut fills) = SimulatedVenue::new();
+        venue.update_mark("BTC", px(100));
+        let ack = venue
+            .submit(limit(OrderSide::Buy, 1, 95, OrderTif::Gtc))
+            .await
+            .unwrap();
+        assert!(matches!(ack.status, AckStatus::Resting { .. }));
+
+        venue.on_market_event(&candle(0, 96, 101, 99));
+        assert!(fills.try_recv().is_err());
+        venue.on_market_event(&candle(1, 94, 100, 99));
+        let fill = fills.try_recv().unwrap();
+        assert_eq!(fill.price, px(95));
+        assert!(venue.open_orders().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stop_fires_inside_the_candle_range() {
+        let (venue, mut fills) = long_venue().await;
+        venue.submit(stop_loss(1, 95)).await.unwrap();
+
+        venue.on_market_event(&candle(0, 96, 102, 101));
+        assert!(fills.try_recv().is_err());
+        venue.on_market_event(&candle(1, 90, 101, 99));
+        let fill = fills.try_recv().unwrap();
+        assert!(!fill.is_buy);
+        assert_eq!(fill.price, px(95));
+    }
+
+    #[tokio::test]
+    async fn stop_ignores_the_wick_printed_before_placement() {
+        let (venue, mut fills) = long_venue().await;
+        venue.on_market_event(&candle(0, 90, 101, 99));
+        venue.submit(stop_loss(1, 95)).await.unwrap();
+
+        // Same bar again, still forming and then closed: the low at 90 came
+        // before the stop existed.
+        venue.on_market_event(&candle(0, 90, 101, 98));
+        venue.on_market_event(&candle(0, 90, 101, 98));
+        assert!(fills.try_recv().is_err());
+
+        venue.on_market_event(&candle(0, 89, 101, 97));
+        assert_eq!(fills.try_recv().unwrap().price, px(95));
+    }
+
+    #[tokio::test]
+    async fn reduce_only_that_would_add_is_rejected() {
+        let (venue, _fills) = SimulatedVenue::new();
+        venue.update_mark("BTC", px(100));
+        let ack = venue.submit(stop_loss(1, 95)).await.unwrap();
+        assert!(ack.is_rejected());
+
+        let (venue, _fills) = long_venue().await;
+        let add = limit(OrderSide::Buy, 1, 101, OrderTif::Ioc).with_reduce_only(true);
+        assert!(venue.submit(add).await.unwrap().is_rejected());
+    }
+
+    #[tokio::test]
+    async fn reduce_only_fills_at_most_the_position() {
+        let (venue, mut fills) = long_venue().await;
+        let close = limit(OrderSide::Sell, 3, 110, OrderTif::Gtc).with_reduce_only(true);
+        venue.submit(close).await.unwrap();
+        venue.on_market_event(&candle(0, 100, 112, 105));
+        assert_eq!(fills.try_recv().unwrap().size, Decimal::ONE);
+    }
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::AppResult;
+use crate::exchange::order_router::{OrderIntent, OrderSide};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OrderRef {
+    Oid(u64),
+    Cloid(Uuid),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenOrder {
+    pub asset: String,
+    pub side: OrderSide,
+    pub size: Decimal,
+    pub limit_px: Decimal,
+    pub oid: u64,
+    pub cloid: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum AckStatus {
+    /// Accepted but not yet resting or filled, e.g. an untriggered order.
+    Pending,
+    Resting {
+        oid: u64,
+    },
+    Filled {
+        oid: u64,
+        total_sz: Decimal,
+        avg_px: Decimal,
+    },
+    Rejected {
+        reason: String,
+    },
+}
+
+/// Immediate venue response to a submit, keyed by the 0x-prefixed cloid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderAck {
+    pub cloid: String,
+    pub status: AckStatus,
+}
+
+impl OrderAck {
+    pub fn is_rejected(&self) -> bool {
+        matches!(self.status, AckStatus::Rejected { .. })
+    }
+}
+
+/// Anything that can take order intents: the Hyperliquid router or an
+/// in-process simulator.
+#[async_trait]
+pub trait ExecutionVenue: Send + Sync {
+    async fn submit(&self, intent: OrderIntent) -> AppResult<OrderAck>;
+
+    /// Submits every intent as one action where the venue supports it. Each
+    /// intent is rounded in place to what the venue accepts and given a cloid,
+    /// so afterwards it holds what was sent. Acks are returned in intent
+    /// order; an intent the venue refuses is acked as rejected on its own.
+    async fn submit_batch(&self, intents: &mut [OrderIntent]) -> AppResult<Vec<OrderAck>>;
+
+    async fn cancel(&self, asset: &str, order: OrderRef) -> AppResult<()>;
+
+    /// One result per order, in request order.
+    async fn cancel_batch(&self, orders: Vec<(String, OrderRef)>) -> AppResult<Vec<AppResult<()>>> {
+        let mut results = Vec::with_capacity(orders.len());
+        for (asset, order) in orders {
+            results.push(self.cancel(&asset, order).await);
+        }
+        Ok(results)
+    }
+
+    /// Cancels every resting order on `asset` and returns how many were
+    /// cancelled.
+    async fn cancel_all(&self, asset: &str) -> AppResult<usize> {
+        let resting: Vec<(String, OrderRef)> = self
+            .open_orders()
+            .await?
+            .into_iter()
+            .filter(|o| o.asset == asset)
+            .map(|o| (o.asset, OrderRef::Oid(o.oid)))
+            .collect();
+        let results = self.cancel_batch(resting).await?;
+        Ok(results.iter().filter(|r| r.is_ok()).count())
+    }
+
+    /// Rounds `intent` in place like `submit_batch` before sending it.
+    async fn modify(&self, oid: u64, intent: &mut OrderIntent) -> AppResult<()>;
+
+    async fn open_orders(&self) -> AppResult<Vec<OpenOrder>>;
+}
@@ -1,44 +1,140 @@
 use alloy::primitives::Address;
 use chrono::{TimeZone, Utc};
 use hyperliquid_rust_sdk::{Message, Subscription};
+use rust_decimal::Decimal;
 use tokio::sync::{broadcast, mpsc};
 use tokio::task::JoinHandle;
 
 use crate::errors::AppResult;
-use crate::exchange::{FillEvent, InfoService};
-use crate::marketdata::events::{CandleEvent, MarketEvent};
+use crate::exchange::order_router::OrderSide;
+use crate::exchange::{FillEvent, InfoService, OrderUpdateEvent};
+use crate::marketdata::events::{BookEvent, BookLevel, CandleEvent, MarketEvent, TradeEvent};
+use crate::marketdata::feeds::{MarketChannel, MarketSubscription};
 
 pub struct MarketStream {
     tx: broadcast::Sender<MarketEvent>,
-    task: JoinHandle<()>,
+    tasks: Vec<JoinHandle<()>>,
 }
 
 impl MarketStream {
-    pub async fn connect_candles(
-        info: InfoService,
+    pub fn new(buffer: usize) -> Self {
+        let (tx, _) = broadcast::channel(buffer);
+        Self {
+            tx,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Forwards `subscription` onto this stream's events.
+    pub async fn add(
+        &mut self,
+        info: &InfoService,
+        subscription: &MarketSubscription,
+    ) -> AppResult<()> {
+        let asset = subscription.asset.clone();
+        match &subscription.channel {
+            MarketChannel::Candles { interval } => {
+                self.add_candles(info, asset, interval.clone()).await
+            }
+            MarketChannel::Trades => self.add_trades(info, asset).await,
+            MarketChannel::Book => self.add_book(info, asset).await,
+        }
+    }
+
+    async fn add_candles(
+        &mut self,
+        info: &InfoService,
         asset: String,
         interval: String,
-        buffer: usize,
-    ) -> AppResult<Self> {
+    ) -> AppResult<()> {
         let mut rx = info
             .subscribe(Subscription::Candle {
                 coin: asset.clone(),
                 interval: interval.clone(),
             })
             .await?;
+        let tx = self.tx.clone();
+        self.tasks.push(tokio::spawn(async move {
+            let mut tracker = CandleTracker::default();
+            while let Some(message) = rx.recv().await {
+                if let Message::Candle(candle) = message
+                    && let Some(event) = Self::map_candle(&asset, &interval, candle)
+                {
+                    for event in tracker.on_update(event) {
+                        let _ = tx.send(MarketEvent::Candle(event));
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    async fn add_book(&mut self, info: &InfoService, asset: String) -> AppResult<()> {
+        let mut rx = info.subscribe(Subscription::L2Book { coin: asset }).await?;
+        let tx = self.tx.clone();
+        self.tasks.push(tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                if let Message::L2Book(book) = message
+                    && let Some(event) = Self::map_book(book)
+                {
+                    let _ = tx.send(MarketEvent::Book(event));
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    async fn add_trades(&mut self, info: &InfoService, asset: String) -> AppResult<()> {
+        let mut rx = info.subscribe(Subscription::Trades { coin: asset }).await?;
+        let tx = self.tx.clone();
+        self.tasks.push(tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                if let Message::Trades(trades) = message {
+                    for event in trades.data.into_iter().filter_map(Self::map_trade) {
+                        let _ = tx.send(MarketEvent::Trade(event));
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    pub fn from_sender(tx: broadcast::Sender<MarketEvent>) -> Self {
+        Self {
+            tx,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Re-broadcasts every event after `observer` has seen it, so the observer
+    /// is always ahead of downstream subscribers.
+    pub fn tap<F>(self, buffer: usize, mut observer: F) -> Self
+    where
+        F: FnMut(&MarketEvent) + Send + 'static,
+    {
+        let mut rx = self.subscribe();
         let (tx, _) = broadcast::channel(buffer);
         let tx_clone = tx.clone();
         let task = tokio::spawn(async move {
-            while let Some(message) = rx.recv().await {
-                if let Message::Candle(candle) = message {
-                    if let Some(event) = Self::map_candle(&asset, &interval, candle) {
-                        let _ = tx_clone.send(MarketEvent::Candle(event));
+            let _upstream = self;
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        observer(&event);
+                        let _ = tx_clone.send(event);
                     }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "tapped market stream lagged");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
                 }
             }
         });
 
-        Ok(Self { tx, task })
+        Self {
+            tx,
+            tasks: vec![task],
+        }
     }
 
     fn map_candle(
@@ -47,27 +143,110 @@ impl MarketStream {
         candle: hyperliquid_rust_sdk::Candle,
     ) -> Option<CandleEvent> {
         let data = candle.data;
-        let close = data.close.parse::<f64>().ok()?;
-        let ts = Utc.timestamp_millis_opt(data.time_close as i64).single()?;
         Some(CandleEvent {
             asset: asset.to_string(),
-            close,
-            timestamp: ts,
+            open: data.open.parse::<Decimal>().ok()?,
+            high: data.high.parse::<Decimal>().ok()?,
+            low: data.low.parse::<Decimal>().ok()?,
+            close: data.close.parse::<Decimal>().ok()?,
+            volume: data.volume.parse::<Decimal>().ok()?,
+            trades: data.num_trades,
+            open_time: Utc.timestamp_millis_opt(data.time_open as i64).single()?,
+            timestamp: Utc.timestamp_millis_opt(data.time_close as i64).single()?,
             interval: interval.to_string(),
+            closed: false,
+        })
+    }
+
+    /// Hyperliquid reports the aggressor as `B` (buy) or `A` (sell).
+    fn map_trade(trade: hyperliquid_rust_sdk::Trade) -> Option<TradeEvent> {
+        Some(TradeEvent {
+            price: trade.px.parse::<Decimal>().ok()?,
+            size: trade.sz.parse::<Decimal>().ok()?,
+            side: if trade.side.eq_ignore_ascii_case("B") {
+                OrderSide::Buy
+            } else {
+                OrderSide::Sell
+            },
+            trade_id: trade.tid,
+            timestamp: Utc.timestamp_millis_opt(trade.time as i64).single()?,
+            asset: trade.coin,
+        })
+    }
+
+    /// Level 0 of the snapshot is bids, level 1 asks.
+    fn map_book(book: hyperliquid_rust_sdk::L2Book) -> Option<BookEvent> {
+        let data = book.data;
+        let mut sides = data.levels.into_iter().map(|levels| {
+            levels
+                .into_iter()
+                .filter_map(|level| {
+                    Some(BookLevel {
+                        price: level.px.parse::<Decimal>().ok()?,
+                        size: level.sz.parse::<Decimal>().ok()?,
+                        orders: level.n,
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+        let (bids, asks) = (sides.next()?, sides.next()?);
+        Some(BookEvent {
+            asset: data.coin,
+            bids,
+            asks,
+            timestamp: Utc.timestamp_millis_opt(data.time as i64).single()?,
         })
     }
 
     pub fn subscribe(&self) -> broadcast::Receiver<MarketEvent> {
         self.tx.subscribe()
     }
+
+    /// Aborts the forwarding tasks; subscribers see the stream end once the
+    /// last sender is gone.
+    pub fn stop(&mut self) {
+        for task in self.tasks.drain(..) {
+            task.abort();
+        }
+    }
+}
+
+/// The candle subscription pushes the forming bar on every trade and never
+/// marks it closed. Repeats are dropped, and the previous bar is emitted once
+/// more as closed when the next one opens.
+#[derive(Default)]
+struct CandleTracker {
+    current: Option<CandleEvent>,
+}
+
+impl CandleTracker {
+    fn on_update(&mut self, candle: CandleEvent) -> Vec<CandleEvent> {
+        let mut events = Vec::with_capacity(2);
+        if let Some(current) = &self.current {
+            if candle.open_time < current.open_time || candle == *current {
+                return events;
+            }
+            if candle.open_time > current.open_time {
+                events.push(CandleEvent {
+                    closed: true,
+                    ..current.clone()
+                });
+            }
+        }
+        self.current = Some(candle.clone());
+        events.push(candle);
+        events
+    }
 }
 
 impl Drop for MarketStream {
     fn drop(&mut self) {
-        self.task.abort();
+        self.stop();
     }
 }
 
+/// Live fills only: the snapshot of recent fills sent on (re)subscribe is
+/// dropped, since positions are already seeded from the exchange.
 pub async fn user_fills_stream(
     info: InfoService,
     address: Address,
@@ -77,9 +256,16 @@ pub async fn user_fills_stream(
     tokio::spawn(async move {
         while let Some(message) = raw_rx.recv().await {
             if let Message::UserFills(fills) = message {
+                if fills.data.is_snapshot == Some(true) {
+                    tracing::debug!(
+                        count = fills.data.fills.len(),
+                        "skipping user fills snapshot"
+                    );
+                    continue;
+                }
                 for trade in fills.data.fills {
                     if let (Ok(price), Ok(size)) =
-                        (trade.px.parse::<f64>(), trade.sz.parse::<f64>())
+                        (trade.px.parse::<Decimal>(), trade.sz.parse::<Decimal>())
                     {
                         let is_buy = trade.side.eq_ignore_ascii_case("B")
                             || trade.side.eq_ignore_ascii_case("buy");
@@ -89,6 +275,8 @@ pub async fn user_fills_stream(
                             size,
                             is_buy,
                             cloid: trade.cloid.clone(),
+                            fee: trade.fee.parse::<Decimal>().unwrap_or_default(),
+                            tid: Some(trade.tid),
                         };
                         let _ = tx.send(event);
                     }
@@ -98,3 +286,95 @@ pub async fn user_fills_stream(
     });
     Ok(rx)
 }
+
+pub async fn order_updates_stream(
+    info: InfoService,
+    address: Address,
+) -> AppResult<mpsc::UnboundedReceiver<OrderUpdateEvent>> {
+    let mut raw_rx = info
+        .subscribe(Subscription::OrderUpdates { user: address })
+        .await?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(message) = raw_rx.recv().await {
+            if let Message::OrderUpdates(updates) = message {
+                for update in updates.data {
+                    let order = update.order;
+                    let (Ok(size), Ok(orig_size), Ok(limit_px)) = (
+                        order.sz.parse::<Decimal>(),
+                        order.orig_sz.parse::<Decimal>(),
+                        order.limit_px.parse::<Decimal>(),
+                    ) else {
+                        continue;
+                    };
+                    let timestamp = Utc
+                        .timestamp_millis_opt(update.status_timestamp as i64)
+                        .single()
+                        .unwrap_or_else(Utc::now);
+                    let event = OrderUpdateEvent {
+                        asset: order.coin,
+                        oid: order.oid,
+                        cloid: order.cloid,
+                        status: update.status,
+                        size,
+                        orig_size,
+                        limit_px,
+                        timestamp,
+                    };
+                    let _ = tx.send(event);
+                }
+            }
+        }
+    });
+    Ok(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(open_minute: i64, close: i64) -> CandleEvent {
+        let open_time = Utc.timestamp_opt(open_minute * 60, 0).unwrap();
+        let close = Decimal::from(close);
+        CandleEvent {
+            asset: "BTC".into(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: Decimal::ZERO,
+            trades: 0,
+            open_time,
+            timestamp: open_time + chrono::Duration::minutes(1),
+            interval: "1m".into(),
+            closed: false,
+        }
+    }
+
+    #[test]
+    fn forming_updates_pass_through_once() {
+        let mut tracker = CandleTracker::default();
+        assert_eq!(tracker.on_update(candle(0, 100)), vec![candle(0, 100)]);
+        assert!(tracker.on_update(candle(0, 100)).is_empty());
+        assert_eq!(tracker.on_update(candle(0, 101)), vec![candle(0, 101)]);
+    }
+
+    #[test]
+    fn next_bar_closes_the_previous_one() {
+        let mut tracker = CandleTracker::default();
+        tracker.on_update(candle(0, 100));
+        tracker.on_update(candle(0, 102));
+        let events = tracker.on_update(candle(1, 103));
+        assert_eq!(events.len(), 2);
+        assert!(events[0].closed);
+        assert_eq!(events[0].close, Decimal::from(102));
+        assert_eq!(events[1], candle(1, 103));
+    }
+
+    #[test]
+    fn stale_bars_are_dropped() {
+        let mut tracker = CandleTracker::default();
+        tracker.on_update(candle(1, 100));
+        assert!(tracker.on_update(candle(0, 99)).is_empty());
+    }
+}
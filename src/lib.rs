@@ -1,4 +1,5 @@
 pub mod app;
+pub mod backtest;
 pub mod config;
 pub mod engine;
 pub mod errors;
@@ -10,6 +11,7 @@ pub mod telemetry;
 pub mod utils;
 
 pub use app::*;
+pub use backtest::*;
 pub use config::*;
 pub use engine::*;
 pub use errors::*;
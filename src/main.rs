@@ -1,4 +1,7 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use rust_decimal::Decimal;
 use snivy::{App, AppResult, Settings, telemetry};
 
 #[derive(Debug, Parser)]
@@ -6,6 +9,23 @@ use snivy::{App, AppResult, Settings, telemetry};
 struct Cli {
     #[arg(short, long, default_value = "configs/default.toml")]
     config: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Trade the enabled strategies against the configured exchange
+    Run,
+    /// Replay stored candles (one JSON CandleEvent per line) through the enabled strategy
+    Backtest {
+        #[arg(long)]
+        data: PathBuf,
+        #[arg(long, default_value = "10000")]
+        initial_capital: Decimal,
+        #[arg(long, default_value = "data/backtest")]
+        output_dir: PathBuf,
+    },
 }
 
 #[tokio::main]
@@ -14,5 +34,21 @@ async fn main() -> AppResult<()> {
     let settings = Settings::load_from(&cli.config)?;
     telemetry::init(&settings.telemetry)?;
     let app = App::new(settings);
-    app.run().await
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => app.run().await,
+        Command::Backtest {
+            data,
+            initial_capital,
+            output_dir,
+        } => {
+            let report = app.backtest(data, initial_capital, output_dir).await?;
+            println!("strategy      {}", report.strategy);
+            println!("candles       {}", report.candles);
+            println!("trades        {}", report.trade_count);
+            println!("pnl           {:.4}", report.pnl);
+            println!("max drawdown  {:.4}%", report.max_drawdown * 100.0);
+            println!("sharpe        {:.4}", report.sharpe);
+            Ok(())
+        }
+    }
 }
@@ -1,19 +1,63 @@
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::exchange::order_router::OrderSide;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CandleEvent {
     pub asset: String,
-    pub close: f64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    /// Base-asset volume traded in the bar.
+    #[serde(default)]
+    pub volume: Decimal,
+    #[serde(default)]
+    pub trades: u64,
+    pub open_time: DateTime<Utc>,
+    /// Close time of the bar.
     pub timestamp: DateTime<Utc>,
     pub interval: String,
+    /// False while the bar is still forming; strategies that trade on bar
+    /// close should skip those updates.
+    #[serde(default = "CandleEvent::default_closed")]
+    pub closed: bool,
+}
+
+impl CandleEvent {
+    fn default_closed() -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeEvent {
     pub asset: String,
-    pub price: f64,
-    pub size: f64,
+    pub price: Decimal,
+    pub size: Decimal,
+    /// Side of the aggressor: `Buy` when a buyer lifted the offer.
+    pub side: OrderSide,
+    pub trade_id: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookLevel {
+    pub price: Decimal,
+    pub size: Decimal,
+    /// Number of resting orders aggregated into the level.
+    pub orders: u64,
+}
+
+/// Aggregated L2 levels, best price first on each side. Hyperliquid sends the
+/// full top of book on every update rather than diffs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookEvent {
+    pub asset: String,
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
     pub timestamp: DateTime<Utc>,
 }
 
@@ -21,4 +65,27 @@ pub struct TradeEvent {
 pub enum MarketEvent {
     Candle(CandleEvent),
     Trade(TradeEvent),
+    Book(BookEvent),
+}
+
+impl MarketEvent {
+    pub fn asset(&self) -> &str {
+        match self {
+            MarketEvent::Candle(candle) => &candle.asset,
+            MarketEvent::Trade(trade) => &trade.asset,
+            MarketEvent::Book(book) => &book.asset,
+        }
+    }
+
+    /// Reference price for marking: candle close, trade price or book mid.
+    pub fn price(&self) -> Option<Decimal> {
+        match self {
+            MarketEvent::Candle(candle) => Some(candle.close),
+            MarketEvent::Trade(trade) => Some(trade.price),
+            MarketEvent::Book(book) => {
+                let (bid, ask) = (book.bids.first()?, book.asks.first()?);
+                Some((bid.price + ask.price) / Decimal::TWO)
+            }
+        }
+    }
 }
@@ -1,28 +1,126 @@
-use std::sync::Arc;
+use std::collections::BTreeSet;
 use std::time::Duration;
 
+use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 use tokio_stream::StreamExt;
 use tokio_stream::wrappers::BroadcastStream;
-use tracing::instrument;
+use tracing::{info, instrument};
 
-use crate::exchange::MarketStream;
+use crate::errors::AppResult;
+use crate::exchange::{InfoService, MarketStream};
 use crate::marketdata::events::MarketEvent;
 
-#[derive(Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "channel", rename_all = "snake_case")]
+pub enum MarketChannel {
+    Candles { interval: String },
+    Trades,
+    Book,
+}
+
+/// One exchange channel for one asset, as declared by `Strategy::subscriptions`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct MarketSubscription {
+    pub asset: String,
+    #[serde(flatten)]
+    pub channel: MarketChannel,
+}
+
+impl MarketSubscription {
+    pub fn candles(asset: &str, interval: &str) -> Self {
+        Self {
+            asset: asset.to_string(),
+            channel: MarketChannel::Candles {
+                interval: interval.to_string(),
+            },
+        }
+    }
+
+    pub fn trades(asset: &str) -> Self {
+        Self {
+            asset: asset.to_string(),
+            channel: MarketChannel::Trades,
+        }
+    }
+
+    pub fn book(asset: &str) -> Self {
+        Self {
+            asset: asset.to_string(),
+            channel: MarketChannel::Book,
+        }
+    }
+
+    pub fn matches(&self, event: &MarketEvent) -> bool {
+        if event.asset() != self.asset {
+            return false;
+        }
+        match (&self.channel, event) {
+            (MarketChannel::Candles { interval }, MarketEvent::Candle(candle)) => {
+                candle.interval == *interval
+            }
+            (MarketChannel::Trades, MarketEvent::Trade(_)) => true,
+            (MarketChannel::Book, MarketEvent::Book(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Multiplexes every strategy's subscriptions into one stream. Each distinct
+/// subscription is opened once however many strategies share it, and events
+/// keep the order in which the exchange pushed them.
 pub struct FeedCoordinator {
-    inner: Arc<MarketStream>,
+    stream: MarketStream,
+    subscriptions: BTreeSet<MarketSubscription>,
 }
 
 impl FeedCoordinator {
     pub fn new(stream: MarketStream) -> Self {
         Self {
-            inner: Arc::new(stream),
+            stream,
+            subscriptions: BTreeSet::new(),
         }
     }
 
+    #[instrument(skip_all)]
+    pub async fn connect(
+        info: &InfoService,
+        subscriptions: impl IntoIterator<Item = MarketSubscription>,
+        buffer: usize,
+    ) -> AppResult<Self> {
+        let subscriptions: BTreeSet<MarketSubscription> = subscriptions.into_iter().collect();
+        let mut stream = MarketStream::new(buffer);
+        for subscription in &subscriptions {
+            info!(asset = %subscription.asset, channel = ?subscription.channel, "subscribing to market data");
+            stream.add(info, subscription).await?;
+        }
+        Ok(Self {
+            stream,
+            subscriptions,
+        })
+    }
+
+    /// Lets `observer` see every event before any subscriber does.
+    pub fn tap<F>(self, buffer: usize, observer: F) -> Self
+    where
+        F: FnMut(&MarketEvent) + Send + 'static,
+    {
+        Self {
+            stream: self.stream.tap(buffer, observer),
+            subscriptions: self.subscriptions,
+        }
+    }
+
+    pub fn subscriptions(&self) -> &BTreeSet<MarketSubscription> {
+        &self.subscriptions
+    }
+
     pub fn subscribe(&self) -> broadcast::Receiver<MarketEvent> {
-        self.inner.subscribe()
+        self.stream.subscribe()
+    }
+
+    pub fn stop(&mut self) {
+        self.stream.stop();
     }
 
     #[instrument(skip_all)]
@@ -30,7 +128,7 @@ impl FeedCoordinator {
     where
         F: FnMut(MarketEvent) + Send + 'static,
     {
-        let mut stream = BroadcastStream::new(self.inner.subscribe());
+        let mut stream = BroadcastStream::new(self.stream.subscribe());
         while let Some(Ok(event)) = stream.next().await {
             handler(event);
         }
@@ -1,10 +1,12 @@
 use std::collections::VecDeque;
 
+use rust_decimal::Decimal;
+
 use crate::utils::math::simple_moving_average;
 
 #[derive(Debug, Clone)]
 pub struct MovingAverage {
-    window: VecDeque<f64>,
+    window: VecDeque<Decimal>,
     period: usize,
 }
 
@@ -16,14 +18,14 @@ impl MovingAverage {
         }
     }
 
-    pub fn seed(&mut self, values: &[f64]) {
+    pub fn seed(&mut self, values: &[Decimal]) {
         self.window.clear();
         for value in values.iter().cloned().take(self.period) {
             self.window.push_back(value);
         }
     }
 
-    pub fn update(&mut self, value: f64) -> Option<f64> {
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
         self.window.push_back(value);
         if self.window.len() > self.period {
             self.window.pop_front();
@@ -35,15 +37,18 @@ impl MovingAverage {
         }
     }
 
-    pub fn current(&self) -> Option<f64> {
+    pub fn current(&self) -> Option<Decimal> {
         if self.window.len() == self.period {
-            Some(self.window.iter().sum::<f64>() / self.period as f64)
+            self.window
+                .iter()
+                .sum::<Decimal>()
+                .checked_div(Decimal::from(self.period))
         } else {
             None
         }
     }
 
-    pub fn values(&self) -> Vec<f64> {
+    pub fn values(&self) -> Vec<Decimal> {
         self.window.iter().cloned().collect()
     }
 
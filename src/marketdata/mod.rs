@@ -1,3 +1,4 @@
 pub mod events;
 pub mod feeds;
 pub mod indicators;
+pub mod order_book;
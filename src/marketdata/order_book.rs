@@ -0,0 +1,111 @@
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::exchange::order_router::OrderSide;
+use crate::marketdata::events::{BookEvent, BookLevel};
+
+/// Latest L2 snapshot for one asset. Bids are sorted highest first, asks
+/// lowest first.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OrderBook {
+    pub asset: String,
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl OrderBook {
+    pub fn new(asset: &str) -> Self {
+        Self {
+            asset: asset.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Replaces the book with `event`; snapshots older than the current one
+    /// are ignored.
+    pub fn apply(&mut self, event: &BookEvent) {
+        if self.updated_at.is_some_and(|at| event.timestamp < at) {
+            return;
+        }
+        self.bids = event.bids.clone();
+        self.asks = event.asks.clone();
+        self.bids.sort_by_key(|level| Reverse(level.price));
+        self.asks.sort_by_key(|level| level.price);
+        self.updated_at = Some(event.timestamp);
+    }
+
+    pub fn best_bid(&self) -> Option<&BookLevel> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&BookLevel> {
+        self.asks.first()
+    }
+
+    pub fn mid(&self) -> Option<Decimal> {
+        let (bid, ask) = (self.best_bid()?, self.best_ask()?);
+        Some((bid.price + ask.price) / Decimal::TWO)
+    }
+
+    pub fn spread(&self) -> Option<Decimal> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    /// Size resting at exactly `price` on either side.
+    pub fn size_at(&self, price: Decimal) -> Decimal {
+        self.bids
+            .iter()
+            .chain(&self.asks)
+            .filter(|level| level.price == price)
+            .map(|level| level.size)
+            .sum()
+    }
+
+    /// Size an order on `side` could take at `limit_px` or better: asks for a
+    /// buy, bids for a sell.
+    pub fn depth_at(&self, side: OrderSide, limit_px: Decimal) -> Decimal {
+        match side {
+            OrderSide::Buy => self
+                .asks
+                .iter()
+                .take_while(|level| level.price <= limit_px)
+                .map(|level| level.size)
+                .sum(),
+            OrderSide::Sell => self
+                .bids
+                .iter()
+                .take_while(|level| level.price >= limit_px)
+                .map(|level| level.size)
+                .sum(),
+        }
+    }
+}
+
+/// Books for every subscribed asset, updated by the engine before strategies
+/// see the book event.
+#[derive(Default)]
+pub struct OrderBooks {
+    books: DashMap<String, OrderBook>,
+}
+
+impl OrderBooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&self, event: &BookEvent) {
+        self.books
+            .entry(event.asset.clone())
+            .or_insert_with(|| OrderBook::new(&event.asset))
+            .apply(event);
+    }
+
+    pub fn get(&self, asset: &str) -> Option<OrderBook> {
+        self.books.get(asset).map(|b| b.value().clone())
+    }
+}
@@ -31,4 +31,13 @@ impl Journal {
         writeln!(file, "{}", serde_json::to_string(&entry).unwrap())?;
         Ok(())
     }
+
+    /// Forces appended records to disk.
+    pub fn sync(&self) -> io::Result<()> {
+        match OpenOptions::new().append(true).open(&self.path) {
+            Ok(file) => file.sync_all(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 }
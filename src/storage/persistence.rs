@@ -1,40 +1,230 @@
-use std::fs;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
-use serde::{Serialize, de::DeserializeOwned};
-use serde_json;
-use tracing::info;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{self, Value};
+use tracing::{info, warn};
 
 use crate::errors::AppResult;
+use crate::utils::time::now;
 
+/// Version given to snapshots written before payloads carried one.
+pub const LEGACY_VERSION: u32 = 0;
+/// Version `save` writes for payloads without their own schema versioning.
+pub const DEFAULT_VERSION: u32 = 1;
+
+/// On-disk envelope around every payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot<T> {
+    pub version: u32,
+    pub saved_at: DateTime<Utc>,
+    pub payload: T,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Stored {
+    Versioned(Snapshot<Value>),
+    Legacy(Value),
+}
+
+impl From<Stored> for Snapshot<Value> {
+    fn from(stored: Stored) -> Self {
+        match stored {
+            Stored::Versioned(snapshot) => snapshot,
+            Stored::Legacy(payload) => Snapshot {
+                version: LEGACY_VERSION,
+                saved_at: DateTime::<Utc>::UNIX_EPOCH,
+                payload,
+            },
+        }
+    }
+}
+
+/// Writes go to a temp file that is fsynced and renamed over `{name}.json`,
+/// so a crash leaves either the old or the new snapshot, never a torn one.
+/// The previous `history` generations are kept as `{name}.1.json`,
+/// `{name}.2.json`, … and loads fall back to them in order.
 #[derive(Clone)]
 pub struct SnapshotStore {
     path: PathBuf,
+    history: usize,
 }
 
 impl SnapshotStore {
     pub fn new(path: impl AsRef<Path>) -> Self {
         Self {
             path: path.as_ref().to_path_buf(),
+            history: 0,
         }
     }
 
+    pub fn with_history(mut self, history: usize) -> Self {
+        self.history = history;
+        self
+    }
+
     pub fn save<T: Serialize>(&self, name: &str, payload: &T) -> AppResult<()> {
+        self.save_versioned(name, DEFAULT_VERSION, payload)
+    }
+
+    pub fn save_versioned<T: Serialize>(
+        &self,
+        name: &str,
+        version: u32,
+        payload: &T,
+    ) -> AppResult<()> {
         fs::create_dir_all(&self.path)?;
-        let file = self.path.join(format!("{name}.json"));
-        let data = serde_json::to_vec_pretty(payload)?;
-        fs::write(&file, data)?;
-        info!(target = "snapshot", file = %file.display(), "snapshot saved");
+        let snapshot = Snapshot {
+            version,
+            saved_at: now(),
+            payload,
+        };
+        let data = serde_json::to_vec_pretty(&snapshot)?;
+
+        let tmp = self.path.join(format!("{name}.json.tmp"));
+        let mut file = File::create(&tmp)?;
+        file.write_all(&data)?;
+        file.sync_all()?;
+        drop(file);
+
+        self.rotate(name)?;
+        let latest = self.file(name, 0);
+        fs::rename(&tmp, &latest)?;
+        sync_dir(&self.path)?;
+        info!(target = "snapshot", file = %latest.display(), version, "snapshot saved");
         Ok(())
     }
 
     pub fn load<T: DeserializeOwned>(&self, name: &str) -> AppResult<Option<T>> {
-        let file = self.path.join(format!("{name}.json"));
-        if !file.exists() {
-            return Ok(None);
+        self.load_with(name, |snapshot| {
+            Ok(serde_json::from_value(snapshot.payload)?)
+        })
+    }
+
+    /// Hands the newest snapshot to `decode`, falling back one generation at
+    /// a time while reading or decoding fails. Errors only when snapshots
+    /// exist and none of them decode.
+    pub fn load_with<T, F>(&self, name: &str, mut decode: F) -> AppResult<Option<T>>
+    where
+        F: FnMut(Snapshot<Value>) -> AppResult<T>,
+    {
+        let mut last_error = None;
+        for generation in 0..=self.history {
+            let file = self.file(name, generation);
+            if !file.exists() {
+                continue;
+            }
+            let decoded = fs::read(&file)
+                .map_err(Into::into)
+                .and_then(|bytes| Ok(serde_json::from_slice::<Stored>(&bytes)?))
+                .and_then(|stored| decode(stored.into()));
+            match decoded {
+                Ok(value) => {
+                    if generation > 0 {
+                        warn!(file = %file.display(), "restored from an older snapshot");
+                    }
+                    return Ok(Some(value));
+                }
+                Err(e) => {
+                    warn!(file = %file.display(), error = %e, "unreadable snapshot, trying previous");
+                    last_error = Some(e);
+                }
+            }
+        }
+        last_error.map_or(Ok(None), Err)
+    }
+
+    /// Shifts each kept generation back by one, dropping the oldest.
+    fn rotate(&self, name: &str) -> AppResult<()> {
+        if self.history == 0 {
+            return Ok(());
+        }
+        for generation in (0..self.history).rev() {
+            let from = self.file(name, generation);
+            if from.exists() {
+                fs::rename(&from, self.file(name, generation + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn file(&self, name: &str, generation: usize) -> PathBuf {
+        match generation {
+            0 => self.path.join(format!("{name}.json")),
+            n => self.path.join(format!("{name}.{n}.json")),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> AppResult<()> {
+    File::open(path)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> AppResult<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn store(history: usize) -> SnapshotStore {
+        let dir = std::env::temp_dir().join(format!("snivy-snapshots-{}", Uuid::new_v4()));
+        SnapshotStore::new(dir).with_history(history)
+    }
+
+    #[test]
+    fn saves_rotate_older_generations() {
+        let store = store(2);
+        for value in 1..=4u32 {
+            store.save("state", &value).unwrap();
         }
-        let bytes = fs::read(file)?;
-        let payload = serde_json::from_slice(&bytes)?;
-        Ok(Some(payload))
+        assert_eq!(store.load::<u32>("state").unwrap(), Some(4));
+        let kept = |generation| fs::read(store.file("state", generation)).ok();
+        assert!(kept(1).is_some());
+        assert!(kept(2).is_some());
+        assert!(kept(3).is_none());
+    }
+
+    #[test]
+    fn corrupt_snapshot_falls_back_a_generation() {
+        let store = store(2);
+        store.save("state", &1u32).unwrap();
+        store.save("state", &2u32).unwrap();
+        fs::write(store.file("state", 0), b"{ not json").unwrap();
+        assert_eq!(store.load::<u32>("state").unwrap(), Some(1));
+    }
+
+    #[test]
+    fn errors_when_no_generation_decodes() {
+        let store = store(1);
+        store.save("state", &1u32).unwrap();
+        store.save("state", &2u32).unwrap();
+        fs::write(store.file("state", 0), b"garbage").unwrap();
+        fs::write(store.file("state", 1), b"garbage").unwrap();
+        assert!(store.load::<u32>("state").is_err());
+    }
+
+    #[test]
+    fn missing_snapshot_loads_as_none() {
+        assert_eq!(store(1).load::<u32>("state").unwrap(), None);
+    }
+
+    #[test]
+    fn unversioned_files_load_as_legacy() {
+        let store = store(0);
+        fs::create_dir_all(&store.path).unwrap();
+        fs::write(store.file("state", 0), b"{\"count\": 3}").unwrap();
+        let version = store
+            .load_with("state", |snapshot| Ok(snapshot.version))
+            .unwrap();
+        assert_eq!(version, Some(LEGACY_VERSION));
     }
 }
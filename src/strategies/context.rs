@@ -1,37 +1,90 @@
 use std::sync::Arc;
 
-use tracing::Span;
+use rust_decimal::Decimal;
+use tracing::{Span, info, warn};
+use uuid::Uuid;
 
-use crate::errors::AppResult;
-use crate::exchange::{OrderIntent, OrderRouter, PositionManager};
+use crate::errors::{AppError, AppResult};
+use crate::exchange::order_router::{
+    OrderTif, OrderTrigger, TpSlBracket, TriggerKind, format_cloid, parse_cloid,
+};
+use crate::exchange::{
+    AckStatus, ExecutionVenue, OrderAck, OrderIntent, OrderManager, OrderRef, PositionManager,
+    TrackedOrder,
+};
+use crate::marketdata::order_book::{OrderBook, OrderBooks};
 use crate::storage::journal::Journal;
+use crate::strategies::{CancelInstruction, ModifyInstruction};
 
 #[derive(Clone)]
 pub struct StrategyContext {
-    order_router: Arc<OrderRouter>,
+    instance_id: String,
+    venue: Arc<dyn ExecutionVenue>,
     positions: Arc<PositionManager>,
+    orders: Arc<OrderManager>,
+    books: Arc<OrderBooks>,
     journal: Arc<Journal>,
     span: Span,
 }
 
 impl StrategyContext {
     pub fn new(
-        order_router: Arc<OrderRouter>,
+        instance_id: &str,
+        venue: Arc<dyn ExecutionVenue>,
         positions: Arc<PositionManager>,
+        orders: Arc<OrderManager>,
+        books: Arc<OrderBooks>,
         journal: Arc<Journal>,
     ) -> Self {
         Self {
-            order_router,
+            instance_id: instance_id.to_string(),
+            venue,
             positions,
+            orders,
+            books,
             journal,
-            span: tracing::info_span!("strategy"),
+            span: tracing::info_span!("strategy", instance = %instance_id),
         }
     }
 
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
     pub fn positions(&self) -> Vec<crate::exchange::position_manager::Position> {
         self.positions.snapshot()
     }
 
+    pub fn position(&self, asset: &str) -> Option<crate::exchange::position_manager::Position> {
+        self.positions.position(asset)
+    }
+
+    pub fn realized_pnl(&self, asset: &str) -> Decimal {
+        self.positions.realized_pnl(asset)
+    }
+
+    pub fn unrealized_pnl(&self, asset: &str) -> Decimal {
+        self.positions.unrealized_pnl(asset)
+    }
+
+    pub fn total_pnl(&self) -> Decimal {
+        self.positions.total_pnl()
+    }
+
+    /// Orders from this instance that are pending, resting or partially filled.
+    pub fn open_orders(&self) -> Vec<TrackedOrder> {
+        self.orders.open_orders(Some(&self.instance_id))
+    }
+
+    pub fn order(&self, cloid: &str) -> Option<TrackedOrder> {
+        self.orders.get(cloid)
+    }
+
+    /// Latest L2 book for `asset`; `None` unless the instance subscribes to it.
+    pub fn book(&self, asset: &str) -> Option<OrderBook> {
+        self.books.get(asset)
+    }
+
     pub fn journal(&self) -> Arc<Journal> {
         self.journal.clone()
     }
@@ -44,11 +97,259 @@ impl StrategyContext {
         self.positions.clone()
     }
 
-    pub fn order_router(&self) -> Arc<OrderRouter> {
-        self.order_router.clone()
+    pub fn orders_handle(&self) -> Arc<OrderManager> {
+        self.orders.clone()
+    }
+
+    pub fn venue(&self) -> Arc<dyn ExecutionVenue> {
+        self.venue.clone()
+    }
+
+    pub async fn submit_intent(&self, intent: OrderIntent) -> AppResult<OrderAck> {
+        let mut acks = self.submit_tracked(vec![intent], None).await?;
+        acks.pop()
+            .ok_or_else(|| AppError::Exchange("venue returned no ack".into()))
+    }
+
+    /// Sends all intents in one venue action where supported; acks come back
+    /// in intent order.
+    pub async fn submit_batch(&self, intents: Vec<OrderIntent>) -> AppResult<Vec<OrderAck>> {
+        self.submit_tracked(intents, None).await
+    }
+
+    async fn submit_tracked(
+        &self,
+        mut intents: Vec<OrderIntent>,
+        group: Option<&str>,
+    ) -> AppResult<Vec<OrderAck>> {
+        // the venue rounds the intents in place; tracking afterwards keeps
+        // local state equal to what was sent
+        let submitted = self.venue.submit_batch(&mut intents).await;
+        let cloids: Vec<String> = intents
+            .iter_mut()
+            .map(|intent| {
+                let cloid = format_cloid(intent.cloid.get_or_insert_with(Uuid::new_v4));
+                self.orders.track(&self.instance_id, intent, &cloid, group);
+                cloid
+            })
+            .collect();
+        match submitted {
+            Ok(acks) => {
+                for ack in &acks {
+                    self.orders.apply_ack(ack);
+                }
+                Ok(acks)
+            }
+            Err(e) => {
+                for cloid in &cloids {
+                    self.orders.reject(cloid, &e.to_string());
+                }
+                Err(e)
+            }
+        }
     }
 
-    pub async fn submit_intent(&self, intent: OrderIntent) -> AppResult<()> {
-        self.order_router.submit(intent.clone()).await.map(|_| ())
+    /// Follow-up once one of this instance's orders has a fill: keeps an
+    /// entry's TP/SL bracket sized to what has filled so far, and cancels the
+    /// other leg once a bracket leg is done filling.
+    pub async fn on_order_fill(&self, order: &TrackedOrder) -> AppResult<()> {
+        if let Some(bracket) = &order.bracket {
+            if order.is_complete() {
+                self.orders.take_bracket(&order.cloid);
+            }
+            self.protect_fill(order, bracket).await?;
+        }
+        if !order.is_complete() {
+            return Ok(());
+        }
+        if let Some(group) = &order.group {
+            let siblings = self
+                .orders
+                .open_siblings(group, &order.cloid)
+                .iter()
+                .filter_map(|sibling| {
+                    Some(CancelInstruction::Order {
+                        asset: sibling.asset.clone(),
+                        order: order_ref(sibling)?,
+                    })
+                })
+                .collect();
+            self.cancel_batch(siblings).await?;
+        }
+        Ok(())
+    }
+
+    /// Places the bracket on the first fill of an entry and resizes the open
+    /// legs to the filled quantity on each later partial fill.
+    async fn protect_fill(&self, entry: &TrackedOrder, bracket: &TpSlBracket) -> AppResult<()> {
+        let legs = self.orders.open_siblings(&entry.cloid, &entry.cloid);
+        if legs.is_empty() {
+            return self.place_bracket(entry, bracket.clone()).await;
+        }
+        for leg in legs {
+            if leg.size == entry.filled {
+                continue;
+            }
+            let Some(oid) = leg.oid else {
+                warn!(leg = %leg.cloid, "bracket leg has no oid yet, cannot resize");
+                continue;
+            };
+            let mut intent = OrderIntent::new(
+                leg.asset.clone(),
+                leg.side,
+                entry.filled,
+                leg.limit_px,
+                leg.tif,
+            )?
+            .with_reduce_only(true)
+            .with_tag(leg.client_tag.clone());
+            if let Some(trigger) = leg.trigger.clone() {
+                intent = intent.with_trigger(trigger);
+            }
+            intent.cloid = parse_cloid(&leg.cloid);
+            info!(leg = %leg.cloid, size = %entry.filled, "resizing bracket leg");
+            self.modify(oid, intent).await?;
+        }
+        Ok(())
+    }
+
+    async fn place_bracket(&self, entry: &TrackedOrder, bracket: TpSlBracket) -> AppResult<()> {
+        let mut legs = Vec::new();
+        for (kind, trigger_px) in [
+            (TriggerKind::Tp, bracket.take_profit),
+            (TriggerKind::Sl, bracket.stop_loss),
+        ] {
+            let Some(trigger_px) = trigger_px else {
+                continue;
+            };
+            let leg = OrderIntent::new(
+                entry.asset.clone(),
+                entry.side.opposite(),
+                entry.filled,
+                trigger_px,
+                OrderTif::Gtc,
+            )?
+            .with_reduce_only(true)
+            .with_tag(format!("{}_{}", entry.client_tag, kind.as_str()))
+            .with_trigger(OrderTrigger {
+                trigger_px,
+                is_market: true,
+                kind,
+            });
+            legs.push(leg);
+        }
+        info!(entry = %entry.cloid, legs = legs.len(), "placing bracket");
+        for ack in self.submit_tracked(legs, Some(&entry.cloid)).await? {
+            if let AckStatus::Rejected { reason } = ack.status {
+                return Err(AppError::Exchange(format!(
+                    "bracket leg {} for {} rejected: {reason}",
+                    ack.cloid, entry.cloid
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn cancel(&self, asset: &str, order: OrderRef) -> AppResult<()> {
+        let cloid = self.orders.resolve(&order).map(|o| o.cloid);
+        self.venue.cancel(asset, order).await?;
+        if let Some(cloid) = cloid {
+            self.orders.mark_cancelled(&cloid);
+        }
+        Ok(())
+    }
+
+    /// Cancels this instance's open orders on `asset`; other instances trading
+    /// the same asset are left alone.
+    pub async fn cancel_all(&self, asset: &str) -> AppResult<usize> {
+        self.cancel_batch(vec![CancelInstruction::AllForAsset(asset.to_string())])
+            .await
+    }
+
+    /// Expands and sends every cancel in one venue action where supported and
+    /// returns how many succeeded. A cancel racing a fill is expected, so
+    /// per-order failures are logged rather than returned.
+    pub async fn cancel_batch(&self, cancels: Vec<CancelInstruction>) -> AppResult<usize> {
+        let mut targets: Vec<(String, OrderRef, Option<String>)> = Vec::new();
+        for cancel in cancels {
+            match cancel {
+                CancelInstruction::Order { asset, order } => {
+                    let cloid = self.orders.resolve(&order).map(|o| o.cloid);
+                    targets.push((asset, order, cloid));
+                }
+                CancelInstruction::AllForAsset(asset) => {
+                    for order in self.open_orders().into_iter().filter(|o| o.asset == asset) {
+                        if let Some(target) = order_ref(&order) {
+                            targets.push((asset.clone(), target, Some(order.cloid)));
+                        }
+                    }
+                }
+            }
+        }
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let requests = targets
+            .iter()
+            .map(|(asset, order, _)| (asset.clone(), order.clone()))
+            .collect();
+        let results = self.venue.cancel_batch(requests).await?;
+        let mut cancelled = 0;
+        for ((asset, order, cloid), result) in targets.into_iter().zip(results) {
+            match result {
+                Ok(()) => {
+                    if let Some(cloid) = cloid {
+                        self.orders.mark_cancelled(&cloid);
+                    }
+                    cancelled += 1;
+                }
+                Err(e) => warn!(asset, order = ?order, error = %e, "cancel failed"),
+            }
+        }
+        Ok(cancelled)
+    }
+
+    /// Builds the full replacement order for a modify from the tracked order,
+    /// returning the oid to amend alongside it.
+    pub fn modified_intent(&self, modify: &ModifyInstruction) -> AppResult<(u64, OrderIntent)> {
+        let order = self
+            .orders
+            .resolve(&modify.order)
+            .filter(|o| o.state.is_open())
+            .ok_or_else(|| AppError::Exchange(format!("order {:?} is not open", modify.order)))?;
+        let oid = order.oid.ok_or_else(|| {
+            AppError::Exchange(format!("order {} has no exchange oid yet", order.cloid))
+        })?;
+        let mut intent = OrderIntent::new(
+            order.asset,
+            order.side,
+            modify.size.unwrap_or(order.size),
+            modify.limit_px.unwrap_or(order.limit_px),
+            order.tif,
+        )?
+        .with_reduce_only(order.reduce_only)
+        .with_tag(order.client_tag);
+        intent.cloid = parse_cloid(&order.cloid);
+        intent.trigger = order.trigger;
+        Ok((oid, intent))
+    }
+
+    pub async fn modify(&self, oid: u64, mut intent: OrderIntent) -> AppResult<()> {
+        self.venue.modify(oid, &mut intent).await?;
+        if let Some(cloid) = &intent.cloid {
+            self.orders
+                .apply_modify(&format_cloid(cloid), intent.size, intent.limit_px);
+        }
+        Ok(())
+    }
+}
+
+/// Prefers the exchange oid; orders not yet acknowledged are addressed by cloid.
+fn order_ref(order: &TrackedOrder) -> Option<OrderRef> {
+    match (order.oid, parse_cloid(&order.cloid)) {
+        (Some(oid), _) => Some(OrderRef::Oid(oid)),
+        (None, Some(cloid)) => Some(OrderRef::Cloid(cloid)),
+        (None, None) => None,
     }
 }
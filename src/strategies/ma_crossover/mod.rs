@@ -2,17 +2,23 @@ use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::{instrument, warn};
 
 use crate::errors::{AppError, AppResult};
-use crate::exchange::order_router::{OrderIntent, OrderSide, OrderTif};
+use crate::exchange::order_router::{OrderIntent, OrderSide, OrderTif, TpSlBracket};
 use crate::marketdata::events::MarketEvent;
+use crate::marketdata::feeds::MarketSubscription;
 use crate::marketdata::indicators::MovingAverage;
-use crate::strategies::{Strategy, StrategyBuilderContext, StrategyContext, StrategyResponse};
-use crate::utils::math::format_decimal;
+use crate::strategies::{
+    CancelInstruction, Strategy, StrategyBuilderContext, StrategyContext, StrategyResponse,
+};
+use crate::utils::math::bps;
 
+/// Prefix of the per-asset key state was saved under before snapshots were
+/// keyed by instance.
 const SNAPSHOT_PREFIX: &str = "ma_crossover";
 
 #[derive(Debug, Clone, Deserialize)]
@@ -21,21 +27,27 @@ pub struct MaCrossoverParams {
     pub short_window: usize,
     pub long_window: usize,
     #[serde(default = "default_trade_size")]
-    pub trade_size: String,
+    pub trade_size: Decimal,
     #[serde(default = "default_interval")]
     pub candle_interval: String,
     #[serde(default = "default_slippage_bps")]
     pub slippage_bps: u32,
     #[serde(default = "default_max_position")]
-    pub max_position: f64,
+    pub max_position: Decimal,
     #[serde(default = "default_order_rate_limit")]
     pub max_order_rate_per_min: u32,
     #[serde(default = "default_bootstrap_candles")]
     pub bootstrap_candles: usize,
+    /// Attach a stop-loss this many bps from the entry price.
+    #[serde(default)]
+    pub stop_loss_bps: Option<u32>,
+    /// Attach a take-profit this many bps from the entry price.
+    #[serde(default)]
+    pub take_profit_bps: Option<u32>,
 }
 
-fn default_trade_size() -> String {
-    "0.01".to_string()
+fn default_trade_size() -> Decimal {
+    Decimal::new(1, 2)
 }
 
 fn default_interval() -> String {
@@ -46,8 +58,8 @@ fn default_slippage_bps() -> u32 {
     5
 }
 
-fn default_max_position() -> f64 {
-    0.05
+fn default_max_position() -> Decimal {
+    Decimal::new(5, 2)
 }
 
 fn default_order_rate_limit() -> u32 {
@@ -60,8 +72,8 @@ fn default_bootstrap_candles() -> usize {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct MaCrossoverSnapshot {
-    short_values: Vec<f64>,
-    long_values: Vec<f64>,
+    short_values: Vec<Decimal>,
+    long_values: Vec<Decimal>,
     last_signal: SignalSide,
 }
 
@@ -77,9 +89,7 @@ pub struct MaCrossoverStrategy {
     short_ma: MovingAverage,
     long_ma: MovingAverage,
     last_signal: SignalSide,
-    info: crate::exchange::InfoService,
-    snapshot_store: crate::storage::persistence::SnapshotStore,
-    snapshot_key: String,
+    info: Option<crate::exchange::InfoService>,
     bootstrapped: bool,
     rate_limiter: OrderRateLimiter,
 }
@@ -98,25 +108,16 @@ impl MaCrossoverBuilder {
         if params.bootstrap_candles < params.long_window {
             params.bootstrap_candles = params.long_window * 2;
         }
-        let snapshot_key = format!("{SNAPSHOT_PREFIX}_{}", params.asset.to_lowercase());
-
         let rate_limit = params.max_order_rate_per_min.max(1);
-        let mut strategy = MaCrossoverStrategy {
+        Ok(Box::new(MaCrossoverStrategy {
             short_ma: MovingAverage::new(params.short_window),
             long_ma: MovingAverage::new(params.long_window),
             params,
             last_signal: SignalSide::Flat,
             info: ctx.info.clone(),
-            snapshot_store: ctx.snapshot_store.clone(),
-            snapshot_key,
             bootstrapped: false,
             rate_limiter: OrderRateLimiter::new(60, rate_limit),
-        };
-
-        // attempt to load cached state immediately
-        strategy.load_from_snapshot()?;
-
-        Ok(Box::new(strategy))
+        }))
     }
 }
 
@@ -126,25 +127,56 @@ impl Strategy for MaCrossoverStrategy {
         "ma_crossover"
     }
 
+    fn subscriptions(&self) -> Vec<MarketSubscription> {
+        vec![MarketSubscription::candles(
+            &self.params.asset,
+            &self.params.candle_interval,
+        )]
+    }
+
+    fn legacy_snapshot_key(&self, _instance_id: &str) -> Option<String> {
+        Some(format!(
+            "{SNAPSHOT_PREFIX}_{}",
+            self.params.asset.to_lowercase()
+        ))
+    }
+
+    fn uses_brackets(&self) -> bool {
+        self.params.stop_loss_bps.is_some() || self.params.take_profit_bps.is_some()
+    }
+
     #[instrument(skip(self, ctx))]
     async fn on_event(
         &mut self,
         ctx: &mut StrategyContext,
         event: MarketEvent,
     ) -> AppResult<StrategyResponse> {
-        if !matches_asset(&event, &self.params.asset) {
+        if event.asset() != self.params.asset {
             return Ok(StrategyResponse::idle());
         }
 
         self.ensure_bootstrap().await?;
         let price = match &event {
+            // the averages are over closed bars; forming-bar updates would
+            // count the same bar many times
+            MarketEvent::Candle(candle) if !candle.closed => {
+                return Ok(StrategyResponse::idle());
+            }
             MarketEvent::Candle(candle) => candle.close,
             MarketEvent::Trade(trade) => trade.price,
+            // the averages are built from traded prices, not quotes
+            MarketEvent::Book(_) => return Ok(StrategyResponse::idle()),
         };
 
         if let Some(intent) = self.evaluate(price, ctx).await? {
-            self.persist_state()?;
-            return Ok(StrategyResponse::with_intent(intent));
+            let mut response = StrategyResponse::with_intent(intent);
+            // closing trades leave the previous entry's bracket legs behind
+            if response.intents[0].reduce_only && self.uses_brackets() {
+                response
+                    .cancels
+                    .push(CancelInstruction::AllForAsset(self.params.asset.clone()));
+            }
+            return Ok(response);
         }
 
         Ok(StrategyResponse::idle())
@@ -157,7 +189,6 @@ impl Strategy for MaCrossoverStrategy {
         _fill: crate::exchange::FillEvent,
     ) -> AppResult<StrategyResponse> {
         self.sync_signal_from_positions(ctx);
-        self.persist_state()?;
         Ok(StrategyResponse::idle())
     }
 
@@ -166,8 +197,14 @@ impl Strategy for MaCrossoverStrategy {
     }
 
     fn restore_state(&mut self, state: Value) {
-        if let Ok(snapshot) = serde_json::from_value::<MaCrossoverSnapshot>(state) {
-            self.restore_from_snapshot(snapshot);
+        match serde_json::from_value::<MaCrossoverSnapshot>(state) {
+            Ok(snapshot) => {
+                self.short_ma.seed(&snapshot.short_values);
+                self.long_ma.seed(&snapshot.long_values);
+                self.last_signal = snapshot.last_signal;
+                self.bootstrapped = true;
+            }
+            Err(e) => warn!(error = %e, "ignoring unreadable MA snapshot"),
         }
     }
 }
@@ -183,8 +220,13 @@ impl MaCrossoverStrategy {
             return Ok(());
         }
 
-        let closes = self
-            .info
+        // without an info client (backtests) the averages warm up from the stream
+        let Some(info) = &self.info else {
+            self.bootstrapped = true;
+            return Ok(());
+        };
+
+        let candles = info
             .candles_snapshot(
                 &self.params.asset,
                 &self.params.candle_interval,
@@ -192,19 +234,18 @@ impl MaCrossoverStrategy {
             )
             .await?;
 
-        for px in closes {
-            self.short_ma.update(px);
-            self.long_ma.update(px);
+        for candle in candles.into_iter().filter(|c| c.closed) {
+            self.short_ma.update(candle.close);
+            self.long_ma.update(candle.close);
         }
 
         self.bootstrapped = true;
-        self.persist_state()?;
         Ok(())
     }
 
     async fn evaluate(
         &mut self,
-        price: f64,
+        price: Decimal,
         ctx: &StrategyContext,
     ) -> AppResult<Option<OrderIntent>> {
         let short = match self.short_ma.update(price) {
@@ -240,31 +281,32 @@ impl MaCrossoverStrategy {
             OrderSide::Sell
         };
         let reduce_only = match side {
-            OrderSide::Buy => net_position < 0.0,
-            OrderSide::Sell => net_position > 0.0,
+            OrderSide::Buy => net_position < Decimal::ZERO,
+            OrderSide::Sell => net_position > Decimal::ZERO,
         };
 
         if !self.within_limits(net_position, &side, reduce_only) {
             return Ok(None);
         }
 
-        let limit_px = self.limit_price(price, &side);
-        let intent = OrderIntent {
-            asset: self.params.asset.clone(),
+        let mut intent = OrderIntent::new(
+            self.params.asset.clone(),
             side,
-            size: self.params.trade_size.clone(),
-            limit_px,
-            tif: OrderTif::Ioc,
-            reduce_only,
-            client_tag: format!("ma_cross_{target_signal:?}"),
-            cloid: None,
-        };
+            self.params.trade_size,
+            self.limit_price(price, &side),
+            OrderTif::Ioc,
+        )?
+        .with_reduce_only(reduce_only)
+        .with_tag(format!("ma_cross_{target_signal:?}"));
+        if !reduce_only && self.uses_brackets() {
+            intent = intent.with_bracket(self.bracket(price, &side));
+        }
 
         self.last_signal = target_signal;
         Ok(Some(intent))
     }
 
-    fn within_limits(&self, net_position: f64, side: &OrderSide, reduce_only: bool) -> bool {
+    fn within_limits(&self, net_position: Decimal, side: &OrderSide, reduce_only: bool) -> bool {
         if reduce_only {
             return true;
         }
@@ -275,59 +317,48 @@ impl MaCrossoverStrategy {
         }
     }
 
-    fn limit_price(&self, price: f64, side: &OrderSide) -> String {
-        let bps = self.params.slippage_bps as f64 / 10_000.0;
-        let adjusted = match side {
-            OrderSide::Buy => price * (1.0 + bps),
-            OrderSide::Sell => price * (1.0 - bps),
+    fn limit_price(&self, price: Decimal, side: &OrderSide) -> Decimal {
+        let slippage = bps(self.params.slippage_bps);
+        match side {
+            OrderSide::Buy => price * (Decimal::ONE + slippage),
+            OrderSide::Sell => price * (Decimal::ONE - slippage),
+        }
+    }
+
+    fn bracket(&self, price: Decimal, side: &OrderSide) -> TpSlBracket {
+        let offset = |value: u32| match side {
+            OrderSide::Buy => bps(value),
+            OrderSide::Sell => -bps(value),
         };
-        format_decimal(adjusted)
+        TpSlBracket {
+            take_profit: self
+                .params
+                .take_profit_bps
+                .map(|value| price * (Decimal::ONE + offset(value))),
+            stop_loss: self
+                .params
+                .stop_loss_bps
+                .map(|value| price * (Decimal::ONE - offset(value))),
+        }
     }
 
-    fn net_position(&self, ctx: &StrategyContext) -> f64 {
-        ctx.positions()
-            .into_iter()
-            .find(|pos| pos.asset == self.params.asset)
+    fn net_position(&self, ctx: &StrategyContext) -> Decimal {
+        ctx.position(&self.params.asset)
             .map(|pos| pos.size)
-            .unwrap_or(0.0)
+            .unwrap_or_default()
     }
 
     fn sync_signal_from_positions(&mut self, ctx: &StrategyContext) {
         let net = self.net_position(ctx);
-        self.last_signal = if net > 0.0 {
+        self.last_signal = if net > Decimal::ZERO {
             SignalSide::Long
-        } else if net < 0.0 {
+        } else if net < Decimal::ZERO {
             SignalSide::Short
         } else {
             SignalSide::Flat
         };
     }
 
-    fn persist_state(&self) -> AppResult<()> {
-        let snapshot = self.build_snapshot();
-        self.snapshot_store
-            .save(&self.snapshot_key, &snapshot)
-            .map_err(|e| AppError::Other(e.to_string()))
-    }
-
-    fn load_from_snapshot(&mut self) -> AppResult<()> {
-        if let Some(snapshot) = self
-            .snapshot_store
-            .load::<MaCrossoverSnapshot>(&self.snapshot_key)
-            .map_err(|e| AppError::Other(e.to_string()))?
-        {
-            self.restore_from_snapshot(snapshot);
-            self.bootstrapped = true;
-        }
-        Ok(())
-    }
-
-    fn restore_from_snapshot(&mut self, snapshot: MaCrossoverSnapshot) {
-        self.short_ma.seed(&snapshot.short_values);
-        self.long_ma.seed(&snapshot.long_values);
-        self.last_signal = snapshot.last_signal;
-    }
-
     fn build_snapshot(&self) -> MaCrossoverSnapshot {
         MaCrossoverSnapshot {
             short_values: self.short_ma.values(),
@@ -337,13 +368,6 @@ impl MaCrossoverStrategy {
     }
 }
 
-fn matches_asset(event: &MarketEvent, asset: &str) -> bool {
-    match event {
-        MarketEvent::Candle(candle) => candle.asset == asset,
-        MarketEvent::Trade(trade) => trade.asset == asset,
-    }
-}
-
 struct OrderRateLimiter {
     max_per_minute: u32,
     timestamps: VecDeque<Instant>,
@@ -377,3 +401,31 @@ impl OrderRateLimiter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_key_ignores_the_instance_id() {
+        let params: MaCrossoverParams = serde_json::from_value(serde_json::json!({
+            "asset": "BTC",
+            "short_window": 5,
+            "long_window": 20,
+        }))
+        .unwrap();
+        let strategy = MaCrossoverStrategy {
+            short_ma: MovingAverage::new(params.short_window),
+            long_ma: MovingAverage::new(params.long_window),
+            params,
+            last_signal: SignalSide::Flat,
+            info: None,
+            bootstrapped: false,
+            rate_limiter: OrderRateLimiter::new(60, 30),
+        };
+        assert_eq!(
+            strategy.legacy_snapshot_key("btc_fast").as_deref(),
+            Some("ma_crossover_btc")
+        );
+    }
+}
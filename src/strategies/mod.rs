@@ -5,25 +5,85 @@ pub mod registry;
 pub use context::StrategyContext;
 pub use registry::{build as build_strategy, register_builtin_strategies};
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::errors::AppResult;
-use crate::exchange::{FillEvent, InfoService, OrderIntent};
+use crate::exchange::{FillEvent, InfoService, OrderIntent, OrderRef};
 use crate::marketdata::events::MarketEvent;
-use crate::storage::persistence::SnapshotStore;
+use crate::marketdata::feeds::MarketSubscription;
+use crate::storage::persistence::{DEFAULT_VERSION, SnapshotStore};
 use hyperliquid_rust_sdk::BaseUrl;
 
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    #[default]
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AlertSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertSeverity::Info => "info",
+            AlertSeverity::Warning => "warning",
+            AlertSeverity::Critical => "critical",
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum StrategyAction {
     None,
-    Alert(String),
+    /// Sent to the configured alert sinks for a human to look at.
+    Alert {
+        severity: AlertSeverity,
+        message: String,
+    },
+}
+
+impl StrategyAction {
+    pub fn alert(severity: AlertSeverity, message: impl Into<String>) -> Self {
+        Self::Alert {
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CancelInstruction {
+    Order {
+        asset: String,
+        order: OrderRef,
+    },
+    /// Every open order this instance has on the asset.
+    AllForAsset(String),
 }
 
+/// Amends a resting order; fields left as `None` keep their current value.
+#[derive(Debug, Clone)]
+pub struct ModifyInstruction {
+    pub order: OrderRef,
+    pub limit_px: Option<Decimal>,
+    pub size: Option<Decimal>,
+}
+
+/// Cancels run first, then modifies, then new intents.
 #[derive(Debug, Clone, Default)]
 pub struct StrategyResponse {
     pub intents: Vec<OrderIntent>,
+    pub cancels: Vec<CancelInstruction>,
+    pub modifies: Vec<ModifyInstruction>,
     pub actions: Vec<StrategyAction>,
 }
 
@@ -35,15 +95,16 @@ impl StrategyResponse {
     pub fn with_intent(intent: OrderIntent) -> Self {
         Self {
             intents: vec![intent],
-            actions: vec![],
+            ..Self::default()
         }
     }
 }
 
 #[derive(Clone)]
 pub struct StrategyBuilderContext {
+    pub instance_id: String,
     pub base_url: BaseUrl,
-    pub info: InfoService,
+    pub info: Option<InfoService>,
     pub snapshot_store: SnapshotStore,
 }
 
@@ -51,6 +112,21 @@ pub struct StrategyBuilderContext {
 pub trait Strategy: Send + Sync {
     fn id(&self) -> &'static str;
 
+    /// Market data this instance needs; `on_event` only receives events
+    /// matching one of these.
+    fn subscriptions(&self) -> Vec<MarketSubscription>;
+
+    /// Cadences at which the engine calls `on_interval`.
+    fn intervals(&self) -> Vec<Duration> {
+        Vec::new()
+    }
+
+    /// Whether entries carry TP/SL brackets. Legs are placed as fills arrive,
+    /// so live trading refuses to start without a fill stream.
+    fn uses_brackets(&self) -> bool {
+        false
+    }
+
     async fn on_event(
         &mut self,
         ctx: &mut StrategyContext,
@@ -79,4 +155,21 @@ pub trait Strategy: Send + Sync {
 
     fn snapshot_state(&self) -> Value;
     fn restore_state(&mut self, state: Value);
+
+    /// Schema version of `snapshot_state`; bump it when the shape changes.
+    fn state_version(&self) -> u32 {
+        DEFAULT_VERSION
+    }
+
+    /// Key the strategy saved its own snapshots under before the engine took
+    /// over; read when `strategy_{instance_id}` does not exist yet.
+    fn legacy_snapshot_key(&self, _instance_id: &str) -> Option<String> {
+        None
+    }
+
+    /// Upgrades state saved at an older `state_version` before it reaches
+    /// `restore_state`. Snapshots predating versioning arrive as version 0.
+    fn migrate_state(&self, _version: u32, state: Value) -> AppResult<Value> {
+        Ok(state)
+    }
 }
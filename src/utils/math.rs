@@ -1,28 +1,41 @@
-pub fn simple_moving_average(window: &[f64]) -> Option<f64> {
+use rust_decimal::Decimal;
+
+pub fn simple_moving_average(window: &[Decimal]) -> Option<Decimal> {
     if window.is_empty() {
         None
     } else {
-        Some(window.iter().sum::<f64>() / window.len() as f64)
+        Some(window.iter().sum::<Decimal>() / Decimal::from(window.len()))
     }
 }
 
-pub fn format_decimal(mut value: f64) -> String {
-    if value == 0.0 {
-        return "0".to_string();
-    }
-    if value.is_sign_negative() && value.abs() < f64::EPSILON {
-        value = 0.0;
-    }
-    let mut out = format!("{:.8}", value);
-    while out.ends_with('0') {
-        out.pop();
+/// Basis points as a fraction, e.g. 25 -> 0.0025.
+pub fn bps(value: u32) -> Decimal {
+    Decimal::new(value as i64, 4)
+}
+
+pub fn max_drawdown(equity: &[f64]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut worst = 0.0;
+    for value in equity {
+        peak = peak.max(*value);
+        if peak > 0.0 {
+            worst = f64::max(worst, (peak - value) / peak);
+        }
     }
-    if out.ends_with('.') {
-        out.pop();
+    worst
+}
+
+pub fn sharpe_ratio(returns: &[f64], periods_per_year: f64) -> f64 {
+    if returns.len() < 2 {
+        return 0.0;
     }
-    if out.is_empty() || out == "-0" {
-        "0".to_string()
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    let std_dev = variance.sqrt();
+    if std_dev == 0.0 {
+        0.0
     } else {
-        out
+        mean / std_dev * periods_per_year.sqrt()
     }
 }
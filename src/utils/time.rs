@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
 
 pub fn now() -> DateTime<Utc> {
     Utc::now()
@@ -20,3 +21,40 @@ pub fn interval_to_millis(interval: &str) -> Option<u64> {
     };
     Some(value.saturating_mul(multiplier))
 }
+
+/// Source of "now" for the engine, so replays can run at data time.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Only moves when `set` is called; backtests advance it candle by candle.
+#[derive(Default)]
+pub struct ManualClock {
+    now: RwLock<DateTime<Utc>>,
+}
+
+impl ManualClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            now: RwLock::new(start),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.write() = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.read()
+    }
+}